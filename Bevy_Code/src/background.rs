@@ -0,0 +1,328 @@
+use std::f32::consts::TAU;
+use bevy::{
+    asset::RenderAssetUsages,
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::{bindings::KeyBindings, camera::CameraSet};
+
+/// Background colors the background key cycles through: black, a dark blue, and a dark gray that
+/// dark bodies stand out against.
+const BACKGROUND_PRESETS: [Color; 3] = [
+    Color::BLACK,
+    Color::srgb(0.02, 0.03, 0.1),
+    Color::srgb(0.15, 0.15, 0.17),
+];
+/// Colors the gradient starts from, top and bottom, when switched to from a solid background.
+pub const DEFAULT_GRADIENT: (Color, Color) = (Color::srgb(0.03, 0.05, 0.18), Color::BLACK);
+/// How far in front of the camera the gradient is drawn, behind the stars but inside the far plane.
+const GRADIENT_DISTANCE: f32 = 950.0;
+
+/// What is drawn behind the bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub enum BackgroundMode {
+    /// A single color filling the background.
+    Solid(Color),
+    /// A vertical blend from the top color to the bottom one, across the view.
+    Gradient(Color, Color),
+}
+
+impl Default for BackgroundMode {
+    fn default() -> Self {
+        Self::Solid(BACKGROUND_PRESETS[0])
+    }
+}
+
+/// Marker for the quad the background gradient is drawn on.
+#[derive(Component)]
+struct BackgroundGradient;
+
+/// Seed the starfield is scattered from, so the sky looks the same every time it is shown.
+const STARFIELD_SEED: u64 = 0x5747;
+/// Size of each star as a fraction of its distance, so they all look the same size.
+const STAR_SIZE: f32 = 0.0015;
+
+/// Settings for the starfield drawn behind the bodies.
+#[derive(Debug, Resource)]
+pub struct StarfieldSettings {
+    pub enabled: bool,
+    /// Number of stars scattered across the sky.
+    pub star_count: usize,
+    /// How far away the stars are drawn. It has to be inside the camera's far plane, and
+    /// further out than any body, so the stars are always behind them.
+    pub distance: f32,
+}
+
+impl Default for StarfieldSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            star_count: 2000,
+            distance: 800.0,
+        }
+    }
+}
+
+/// Marker for the mesh holding every star of the starfield.
+#[derive(Component)]
+struct Starfield;
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ClearColor(BACKGROUND_PRESETS[0]))
+            .init_resource::<BackgroundMode>()
+            .init_resource::<StarfieldSettings>()
+            .add_systems(Startup, setup_background_gradient)
+            .add_systems(Update, (cycle_background, apply_background_mode.run_if(resource_changed::<BackgroundMode>)).chain())
+            .add_systems(Update, place_background_gradient.after(CameraSet::Apply))
+            .add_systems(Update, toggle_starfield)
+            .add_systems(Update, rebuild_starfield.run_if(resource_changed::<StarfieldSettings>))
+            // The sky moves with the camera so it looks infinitely far away, once the camera has moved.
+            .add_systems(Update, center_starfield.after(CameraSet::Apply));
+    }
+}
+
+/// A system to switch the background to the next preset color. A custom color or a gradient
+/// picked in the control panel goes back to the first preset.
+fn cycle_background(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut background_mode: ResMut<BackgroundMode>,
+) {
+    if key_input.just_pressed(key_bindings.cycle_background) {
+        *background_mode = match *background_mode {
+            BackgroundMode::Solid(color) => BackgroundMode::Solid(next_background(color)),
+            BackgroundMode::Gradient(..) => BackgroundMode::Solid(BACKGROUND_PRESETS[0]),
+        };
+    }
+}
+
+/// A function to add the quad the background gradient is drawn on, hidden until there is a gradient.
+fn setup_background_gradient(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let (top, bottom) = DEFAULT_GRADIENT;
+    commands.spawn((
+        BackgroundGradient,
+        Name::new("Background gradient"),
+        Mesh3d(meshes.add(gradient_mesh(top, bottom))),
+        // Unlit, so the colors are exactly the ones picked whatever the lighting.
+        MeshMaterial3d(materials.add(StandardMaterial {
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        })),
+        Transform::default(),
+        Visibility::Hidden,
+        NotShadowCaster,
+        NotShadowReceiver,
+    ));
+}
+
+/// A system to show the background the mode asks for. The clear color shows through whenever the
+/// gradient is hidden, so it is set to match either way.
+fn apply_background_mode(
+    background_mode: Res<BackgroundMode>,
+    mut clear_color: ResMut<ClearColor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    gradient: Option<Single<(&Mesh3d, &mut Visibility), With<BackgroundGradient>>>,
+) {
+    let (clear, gradient_colors) = match *background_mode {
+        BackgroundMode::Solid(color) => (color, None),
+        BackgroundMode::Gradient(top, bottom) => (bottom, Some((top, bottom))),
+    };
+    if clear_color.0 != clear {
+        clear_color.0 = clear;
+    }
+
+    let Some(mut gradient) = gradient else {
+        return;
+    };
+    let (mesh, visibility) = &mut *gradient;
+    visibility.set_if_neq(if gradient_colors.is_some() { Visibility::Inherited } else { Visibility::Hidden });
+    if let (Some((top, bottom)), Some(mesh)) = (gradient_colors, meshes.get_mut(&mesh.0)) {
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, gradient_colors_attribute(top, bottom));
+    }
+}
+
+/// A system to keep the gradient filling the view, far in front of the camera.
+fn place_background_gradient(
+    camera: Single<(&Transform, &Projection), With<Camera>>,
+    mut gradient: Single<&mut Transform, (With<BackgroundGradient>, Without<Camera>)>,
+) {
+    let (camera_transform, projection) = *camera;
+    // A little larger than the view, so the edges never show.
+    let size = match projection {
+        Projection::Perspective(perspective) => {
+            let height = 2.0 * GRADIENT_DISTANCE * (perspective.fov / 2.0).tan() * 1.05;
+            Vec2::new(height * perspective.aspect_ratio, height)
+        }
+        _ => Vec2::splat(GRADIENT_DISTANCE * 4.0),
+    };
+    let transform = Transform {
+        translation: camera_transform.translation + camera_transform.forward() * GRADIENT_DISTANCE,
+        rotation: camera_transform.rotation,
+        scale: size.extend(1.0),
+    };
+    if **gradient != transform {
+        **gradient = transform;
+    }
+}
+
+/// A function to build a unit quad facing the camera, with the top and bottom colors at its edges.
+fn gradient_mesh(top: Color, bottom: Color) -> Mesh {
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.5, 0.5, 0.0], [-0.5, 0.5, 0.0]],
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, gradient_colors_attribute(top, bottom))
+        .with_inserted_indices(Indices::U32(vec![0, 1, 2, 0, 2, 3]))
+}
+
+/// A function to lay out the gradient's vertex colors, bottom two then top two like the quad's corners.
+fn gradient_colors_attribute(top: Color, bottom: Color) -> Vec<[f32; 4]> {
+    let top = top.to_linear().to_f32_array();
+    let bottom = bottom.to_linear().to_f32_array();
+    vec![bottom, bottom, top, top]
+}
+
+/// A system to show or hide the starfield.
+fn toggle_starfield(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut starfield_settings: ResMut<StarfieldSettings>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_starfield) {
+        starfield_settings.enabled = !starfield_settings.enabled;
+        info!("Starfield: {}", starfield_settings.enabled);
+    }
+}
+
+/// A system to replace the starfield whenever its settings change. Turned off, nothing is left
+/// behind, so the background is just the clear color again.
+fn rebuild_starfield(
+    mut commands: Commands,
+    starfield_settings: Res<StarfieldSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    camera: Option<Single<&GlobalTransform, With<Camera>>>,
+    starfields: Query<Entity, With<Starfield>>,
+) {
+    for entity in &starfields {
+        commands.entity(entity).despawn();
+    }
+    if !starfield_settings.enabled {
+        return;
+    }
+
+    let mut rng = StdRng::seed_from_u64(STARFIELD_SEED);
+    let mesh = starfield_mesh(starfield_settings.star_count, starfield_settings.distance, &mut rng);
+    commands.spawn((
+        Starfield,
+        Name::new("Starfield"),
+        Mesh3d(meshes.add(mesh)),
+        // Unlit and no brighter than white, so the stars aren't lit by the star and don't bloom.
+        MeshMaterial3d(materials.add(StandardMaterial {
+            unlit: true,
+            // The faces aren't wound consistently, and a star this small looks the same either way.
+            cull_mode: None,
+            ..default()
+        })),
+        Transform::from_translation(camera.map_or(Vec3::ZERO, |camera| camera.translation())),
+        NotShadowCaster,
+        NotShadowReceiver,
+    ));
+}
+
+/// A system to keep the starfield centered on the camera.
+fn center_starfield(
+    camera: Single<&GlobalTransform, With<Camera>>,
+    mut starfield: Single<&mut Transform, (With<Starfield>, Without<Camera>)>,
+) {
+    let translation = camera.translation();
+    if starfield.translation != translation {
+        starfield.translation = translation;
+    }
+}
+
+/// A function to build a mesh of stars scattered evenly over a sphere, each a tiny octahedron of
+/// its own brightness.
+fn starfield_mesh(star_count: usize, distance: f32, rng: &mut impl Rng) -> Mesh {
+    let size = distance * STAR_SIZE;
+    let mut positions = Vec::with_capacity(star_count * 6);
+    let mut colors = Vec::with_capacity(star_count * 6);
+    let mut indices = Vec::with_capacity(star_count * 24);
+    for star in 0..star_count {
+        // Uniform in height and angle is uniform over the sphere.
+        let height: f32 = rng.random_range(-1.0..1.0);
+        let angle = rng.random_range(0.0..TAU);
+        let ring = (1.0 - height * height).sqrt();
+        let center = Vec3::new(ring * angle.cos(), height, ring * angle.sin()) * distance;
+        // Most stars are faint, with the odd bright one.
+        let brightness = rng.random_range(0.2f32..1.0).powi(3);
+        let scale = size * (0.5 + brightness);
+
+        let first = (star * 6) as u32;
+        for offset in [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z, Vec3::NEG_Z] {
+            positions.push((center + offset * scale).to_array());
+            colors.push([brightness, brightness, brightness, 1.0]);
+        }
+        // The eight faces of the octahedron, one for each pair of X and Y tips around each Z tip.
+        for (x, y) in [(0, 2), (2, 1), (1, 3), (3, 0)] {
+            for z in [4, 5] {
+                indices.extend([first + x, first + y, first + z]);
+            }
+        }
+    }
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+/// A function to find the preset background color after this one.
+fn next_background(current: Color) -> Color {
+    BACKGROUND_PRESETS
+        .iter()
+        .position(|&preset| preset == current)
+        .map_or(BACKGROUND_PRESETS[0], |index| BACKGROUND_PRESETS[(index + 1) % BACKGROUND_PRESETS.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_cycles_through_the_presets() {
+        let mut color = BACKGROUND_PRESETS[0];
+        for expected in BACKGROUND_PRESETS.iter().cycle().skip(1).take(BACKGROUND_PRESETS.len()) {
+            color = next_background(color);
+            assert_eq!(color, *expected);
+        }
+        assert_eq!(next_background(Color::srgb(0.5, 0.2, 0.9)), BACKGROUND_PRESETS[0]);
+    }
+
+    #[test]
+    fn stars_sit_on_the_sky_sphere() {
+        let mut rng = StdRng::seed_from_u64(STARFIELD_SEED);
+        let mesh = starfield_mesh(100, 800.0, &mut rng);
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap();
+        assert_eq!(positions.len(), 600);
+        assert_eq!(mesh.indices().unwrap().len(), 100 * 24);
+
+        // Each star's tips straddle the sphere, no further from it than the biggest star is wide.
+        for star in positions.chunks(6) {
+            let center = (Vec3::from(star[0]) + Vec3::from(star[1])) / 2.0;
+            assert!((center.length() - 800.0).abs() < 1e-2);
+            assert!(Vec3::from(star[0]).distance(center) <= 800.0 * STAR_SIZE * 1.5 + 1e-3);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Instant;
+use bevy::{
+    asset::AssetPlugin,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+};
+use crate::bodies::{BodiesPlugin, Body, PhysicsSettings, SimSeed, SimulationParams};
+
+/// Number of physics ticks that have run since it was last reset.
+#[derive(Debug, Default, Resource)]
+struct TickCount(u32);
+
+/// A function to run the physics without rendering for a number of ticks, then print how long they took.
+pub fn run(ticks: u32, params: SimulationParams, physics_settings: PhysicsSettings, seed: SimSeed) {
+    let mut app = headless_app(params, physics_settings, seed);
+    app.world_mut().resource_mut::<TickCount>().0 = 0;
+
+    let start = Instant::now();
+    while app.world().resource::<TickCount>().0 < ticks {
+        app.update();
+    }
+    let elapsed = start.elapsed();
+
+    let body_count = app.world_mut().query_filtered::<(), With<Body>>().iter(app.world()).count();
+    println!(
+        "Ran {ticks} ticks with {body_count} bodies in {elapsed:.2?}, {:.3?} per tick, {:.1} ticks per second.",
+        elapsed / ticks.max(1),
+        f64::from(ticks) / elapsed.as_secs_f64(),
+    );
+}
+
+/// A function to build an app running the physics without a window or renderer, one tick per
+/// update, with the bodies already spawned. None of the physics reads rendering resources, but
+/// spawning bodies still gives them mesh and material handles, so those asset types are registered
+/// here without anything ever drawing them. Forces on the GPU need the renderer, so they are
+/// summed on the CPU here whatever the settings say.
+fn headless_app(params: SimulationParams, physics_settings: PhysicsSettings, seed: SimSeed) -> App {
+    let mut app = App::new();
+    // Only what the bodies plugin needs to run unchanged, without a window or renderer.
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), TransformPlugin))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .init_resource::<TickCount>()
+        .insert_resource(params)
+        .insert_resource(seed)
+        .insert_resource(PhysicsSettings { gpu_forces: false, ..physics_settings })
+        .add_plugins(BodiesPlugin)
+        .add_systems(FixedLast, |mut tick_count: ResMut<TickCount>| tick_count.0 += 1);
+
+    // Advance time by exactly one timestep per update, so each update runs one physics tick
+    // however long it takes in real time.
+    let timestep = app.world().resource::<Time<Fixed>>().timestep();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+    // The first update runs startup, spawning the bodies, and isn't timed.
+    app.finish();
+    app.cleanup();
+    app.update();
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bodies::{Star, Thermostat};
+    use super::*;
+
+    #[test]
+    fn zero_bodies_step_without_panicking() {
+        // With and without a star, and with the systems that only run on request turned on too.
+        for star in [true, false] {
+            let params = SimulationParams { body_count: 0, ..default() };
+            let physics_settings = PhysicsSettings { fragmentation_threshold: 10.0, ..default() };
+            let mut app = headless_app(params, physics_settings, SimSeed(Some(7)));
+            if !star {
+                let stars: Vec<Entity> = app.world_mut().query_filtered::<Entity, With<Star>>().iter(app.world()).collect();
+                for entity in stars {
+                    app.world_mut().despawn(entity);
+                }
+            }
+            app.world_mut().resource_mut::<Thermostat>().enabled = true;
+            app.world_mut().resource_mut::<TickCount>().0 = 0;
+
+            for _ in 0..10 {
+                app.update();
+            }
+            assert!(app.world().resource::<TickCount>().0 >= 10);
+            let body_count = app.world_mut().query_filtered::<(), With<Body>>().iter(app.world()).count();
+            assert_eq!(body_count, 0);
+        }
+    }
+}
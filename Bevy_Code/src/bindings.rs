@@ -0,0 +1,161 @@
+use std::fs;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// File the key bindings are loaded from at startup, if it exists.
+const BINDINGS_PATH: &str = "key_bindings.ron";
+
+/// The keys and mouse buttons bound to each action.
+/// Actions missing from the bindings file keep their default binding.
+#[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_forward: Vec<KeyCode>,
+    pub move_back: Vec<KeyCode>,
+    pub move_left: Vec<KeyCode>,
+    pub move_right: Vec<KeyCode>,
+    pub move_up: Vec<KeyCode>,
+    pub move_down: Vec<KeyCode>,
+    pub orbit_button: MouseButton,
+    pub pan_button: MouseButton,
+    /// Clicking selects a body, while dragging is left to whatever else the button is bound to.
+    pub select_button: MouseButton,
+    pub toggle_free_fly: KeyCode,
+    pub roll_left: KeyCode,
+    pub roll_right: KeyCode,
+    pub reset_roll: KeyCode,
+    pub invert_pitch: KeyCode,
+    pub invert_yaw: KeyCode,
+    /// Held while pressing a number key to store a camera preset instead of recalling it.
+    pub store_preset: Vec<KeyCode>,
+    pub more_bodies: KeyCode,
+    pub fewer_bodies: KeyCode,
+    pub pause: KeyCode,
+    pub save_state: KeyCode,
+    pub load_state: KeyCode,
+    pub screenshot: KeyCode,
+    pub toggle_center_of_mass: KeyCode,
+    pub toggle_centroid: KeyCode,
+    pub toggle_overlay: KeyCode,
+    pub toggle_performance_overlay: KeyCode,
+    pub toggle_bloom: KeyCode,
+    pub cycle_background: KeyCode,
+    pub toggle_starfield: KeyCode,
+    pub cycle_color_mode: KeyCode,
+    pub toggle_force_vectors: KeyCode,
+    pub toggle_shadows: KeyCode,
+    pub dim_ambient_light: KeyCode,
+    pub brighten_ambient_light: KeyCode,
+    /// Switches between lighting by the star and by a distant sun.
+    pub toggle_sun_light: KeyCode,
+    pub turn_sun_left: KeyCode,
+    pub turn_sun_right: KeyCode,
+    pub toggle_velocity_vectors: KeyCode,
+    pub toggle_labels: KeyCode,
+    pub toggle_trails: KeyCode,
+    pub toggle_shared_materials: KeyCode,
+    pub toggle_reference_grid: KeyCode,
+    pub export_snapshot: KeyCode,
+    /// Writes a CSV of every body for analysis, while paused.
+    pub export_diagnostics: KeyCode,
+    pub top_down_view: KeyCode,
+    /// Zooms the orbit camera out or in to fit every body in view.
+    pub frame_system: KeyCode,
+    pub toggle_control_panel: KeyCode,
+    /// Held while clicking a body to add it to or remove it from the selection.
+    pub extend_selection: Vec<KeyCode>,
+    pub link_spring: KeyCode,
+    pub toggle_anchor: KeyCode,
+    pub toggle_force_field: KeyCode,
+    pub toggle_bounding_spheres: KeyCode,
+    /// Starts the simulation over with a fresh cloud of bodies.
+    pub reset_simulation: KeyCode,
+    /// Held while clicking a body to have the camera follow it.
+    pub follow_body: Vec<KeyCode>,
+    pub stop_following: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            move_forward: vec![KeyCode::KeyW, KeyCode::ArrowUp],
+            move_back: vec![KeyCode::KeyS, KeyCode::ArrowDown],
+            move_left: vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+            move_right: vec![KeyCode::KeyD, KeyCode::ArrowRight],
+            move_up: vec![KeyCode::Space, KeyCode::Enter],
+            move_down: vec![KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            orbit_button: MouseButton::Right,
+            pan_button: MouseButton::Left,
+            select_button: MouseButton::Left,
+            toggle_free_fly: KeyCode::KeyF,
+            roll_left: KeyCode::KeyQ,
+            roll_right: KeyCode::KeyE,
+            reset_roll: KeyCode::KeyR,
+            invert_pitch: KeyCode::KeyY,
+            invert_yaw: KeyCode::KeyX,
+            store_preset: vec![KeyCode::ControlLeft, KeyCode::ControlRight],
+            more_bodies: KeyCode::BracketRight,
+            fewer_bodies: KeyCode::BracketLeft,
+            // Space already moves the camera up, so pausing gets its own key.
+            pause: KeyCode::KeyP,
+            save_state: KeyCode::F5,
+            load_state: KeyCode::F9,
+            screenshot: KeyCode::F12,
+            toggle_center_of_mass: KeyCode::KeyC,
+            toggle_centroid: KeyCode::KeyV,
+            toggle_overlay: KeyCode::F3,
+            toggle_performance_overlay: KeyCode::F10,
+            toggle_bloom: KeyCode::KeyB,
+            cycle_background: KeyCode::KeyU,
+            toggle_starfield: KeyCode::Semicolon,
+            cycle_color_mode: KeyCode::KeyM,
+            toggle_force_vectors: KeyCode::F1,
+            toggle_shadows: KeyCode::KeyL,
+            dim_ambient_light: KeyCode::Comma,
+            brighten_ambient_light: KeyCode::Period,
+            toggle_sun_light: KeyCode::Slash,
+            turn_sun_left: KeyCode::Minus,
+            turn_sun_right: KeyCode::Equal,
+            toggle_velocity_vectors: KeyCode::F2,
+            toggle_labels: KeyCode::KeyT,
+            toggle_trails: KeyCode::KeyI,
+            toggle_shared_materials: KeyCode::KeyK,
+            toggle_reference_grid: KeyCode::KeyG,
+            export_snapshot: KeyCode::F6,
+            export_diagnostics: KeyCode::Quote,
+            top_down_view: KeyCode::KeyO,
+            // A already moves the camera left, so framing gets its own key.
+            frame_system: KeyCode::KeyZ,
+            toggle_control_panel: KeyCode::F7,
+            extend_selection: vec![KeyCode::ControlLeft, KeyCode::ControlRight],
+            link_spring: KeyCode::KeyJ,
+            toggle_anchor: KeyCode::KeyN,
+            toggle_force_field: KeyCode::F8,
+            toggle_bounding_spheres: KeyCode::KeyH,
+            reset_simulation: KeyCode::Backspace,
+            follow_body: vec![KeyCode::AltLeft, KeyCode::AltRight],
+            stop_following: KeyCode::Escape,
+        }
+    }
+}
+
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_key_bindings());
+    }
+}
+
+/// A function to read the key bindings file, falling back to the defaults if it can't be used.
+fn load_key_bindings() -> KeyBindings {
+    // Without a bindings file the defaults are used as they are.
+    let Ok(contents) = fs::read_to_string(BINDINGS_PATH) else {
+        return KeyBindings::default();
+    };
+
+    ron::from_str(&contents).unwrap_or_else(|err| {
+        warn!("Using default key bindings, {BINDINGS_PATH} could not be read: {err}");
+        KeyBindings::default()
+    })
+}
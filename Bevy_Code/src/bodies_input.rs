@@ -0,0 +1,268 @@
+use std::{fs, path::Path, time::{SystemTime, UNIX_EPOCH}};
+use bevy::prelude::*;
+use crate::{
+    bindings::KeyBindings,
+    coloring::{neighbor_count, ColorMode, DensityColorSettings},
+    grid::SpatialGrid,
+    materials::MaterialPalette,
+};
+use crate::bodies::{
+    spawn_body, Anchored, BaseColor, Body, BodyMeshes, Charge, Damping, GravityScale, LastPos, Mass, PhysicsSettings,
+    PhysicsTicks, Radius, RespawnBodies, SimulationParams, SimulationState,
+};
+use crate::persistence::{
+    body_descriptor, body_snapshot, diagnostics_csv, load_snapshot, save_snapshot, BodyDiagnostics, Snapshot,
+    SNAPSHOT_DIR, SNAPSHOT_VERSION, STATE_PATH,
+};
+
+/// Number of bodies added or removed per press of the body count keys.
+const BODY_COUNT_STEP: usize = 10;
+
+/// The keyboard controls for the simulation the library runs, which knows nothing of keys itself.
+pub struct BodiesInputPlugin;
+
+impl Plugin for BodiesInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            toggle_pause,
+            reset_on_key,
+            adjust_body_count,
+            cycle_color_mode,
+            toggle_shared_materials,
+            save_on_key,
+            load_on_key,
+            // Bodies move between ticks while running, so only a paused simulation is exported.
+            export_diagnostics_on_key.run_if(in_state(SimulationState::Paused)),
+        ));
+    }
+}
+
+/// A system to pause or resume the physics simulation.
+fn toggle_pause(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    simulation_state: Res<State<SimulationState>>,
+    mut next_simulation_state: ResMut<NextState<SimulationState>>,
+) {
+    if key_input.just_pressed(key_bindings.pause) {
+        next_simulation_state.set(match simulation_state.get() {
+            SimulationState::Running => SimulationState::Paused,
+            SimulationState::Paused => SimulationState::Running,
+        });
+    }
+}
+
+/// A system to start the simulation over with the reset key.
+fn reset_on_key(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut respawn_events: EventWriter<RespawnBodies>,
+) {
+    if key_input.just_pressed(key_bindings.reset_simulation) {
+        respawn_events.write(RespawnBodies);
+    }
+}
+
+/// A system to change the body count with the more and fewer bodies keys.
+fn adjust_body_count(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut params: ResMut<SimulationParams>,
+) {
+    if key_input.just_pressed(key_bindings.more_bodies) {
+        params.body_count += BODY_COUNT_STEP;
+        info!("Body count set to {}.", params.body_count);
+    }
+    if key_input.just_pressed(key_bindings.fewer_bodies) {
+        params.body_count = params.body_count.saturating_sub(BODY_COUNT_STEP);
+        info!("Body count set to {}.", params.body_count);
+    }
+}
+
+/// A system to step through the color modes.
+fn cycle_color_mode(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut color_mode: ResMut<ColorMode>,
+) {
+    if key_input.just_pressed(key_bindings.cycle_color_mode) {
+        *color_mode = match *color_mode {
+            ColorMode::Random => ColorMode::ByMass,
+            ColorMode::ByMass => ColorMode::BySpeed,
+            ColorMode::BySpeed => ColorMode::ByDensity,
+            ColorMode::ByDensity => ColorMode::Random,
+        };
+        info!("Color mode: {:?}", *color_mode);
+    }
+}
+
+/// A system to switch between unique and shared body materials.
+fn toggle_shared_materials(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut palette: ResMut<MaterialPalette>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut color_mode: ResMut<ColorMode>,
+    mut query: Query<&mut MeshMaterial3d<StandardMaterial>, With<Body>>,
+) {
+    if !key_input.just_pressed(key_bindings.toggle_shared_materials) {
+        return;
+    }
+    palette.shared = !palette.shared;
+
+    // Every body gets a new handle, so no body is left sharing a material it will recolor in
+    // place, and the old unique materials are freed once their last handle is dropped.
+    for mut material in &mut query {
+        let color = materials.get(&material.0).map_or(Color::WHITE, |material| material.base_color);
+        material.0 = palette.material(&mut materials, color);
+    }
+
+    // Repaint every body, since switching to unique materials leaves them with palette colors.
+    color_mode.set_changed();
+    info!("Shared materials: {}", palette.shared);
+}
+
+/// A system to quick save the simulation, or export it to a new timestamped snapshot.
+#[allow(clippy::type_complexity)]
+fn save_on_key(
+    time: Res<Time<Fixed>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    params: Res<SimulationParams>,
+    physics_settings: Res<PhysicsSettings>,
+    query: Query<
+        (
+            &Transform,
+            &LastPos,
+            &Mass,
+            &Radius,
+            &BaseColor,
+            Option<&Charge>,
+            Option<&GravityScale>,
+            Option<&Damping>,
+            Has<Anchored>,
+        ),
+        With<Body>,
+    >,
+) {
+    let path = if key_input.just_pressed(key_bindings.save_state) {
+        Path::new(STATE_PATH).to_path_buf()
+    } else if key_input.just_pressed(key_bindings.export_snapshot) {
+        if let Err(err) = fs::create_dir_all(SNAPSHOT_DIR) {
+            error!("Cannot create the {SNAPSHOT_DIR} directory for snapshots: {err}");
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        Path::new(SNAPSHOT_DIR).join(format!("snapshot_{timestamp}.json"))
+    } else {
+        return;
+    };
+
+    let timestep = physics_settings.substep(time.timestep()).as_secs_f32();
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        params: params.clone(),
+        physics: physics_settings.clone(),
+        bodies: query
+            .iter()
+            .map(|(transform, last_pos, mass, radius, base_color, charge, gravity_scale, damping, anchored)| {
+                body_snapshot(transform, last_pos, mass, radius, base_color, charge, gravity_scale, damping, anchored, timestep)
+            })
+            .collect(),
+    };
+
+    match save_snapshot(&path, &snapshot) {
+        Ok(()) => info!("Saved {} bodies to {}.", snapshot.bodies.len(), path.display()),
+        Err(err) => error!("Failed to save the simulation state to {}: {err}", path.display()),
+    }
+}
+
+/// A system to export every body's position, velocity, size, and neighbor count to a CSV named
+/// after the current physics tick.
+#[allow(clippy::too_many_arguments)]
+fn export_diagnostics_on_key(
+    time: Res<Time<Fixed>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    physics_settings: Res<PhysicsSettings>,
+    ticks: Res<PhysicsTicks>,
+    grid: Res<SpatialGrid>,
+    density_color_settings: Res<DensityColorSettings>,
+    query: Query<(Entity, &Transform, &LastPos, &Mass, &Radius), With<Body>>,
+) {
+    if !key_input.just_pressed(key_bindings.export_diagnostics) {
+        return;
+    }
+    if let Err(err) = fs::create_dir_all(SNAPSHOT_DIR) {
+        error!("Cannot create the {SNAPSHOT_DIR} directory for snapshots: {err}");
+        return;
+    }
+
+    let timestep = physics_settings.substep(time.timestep()).as_secs_f32();
+    let mut rows: Vec<BodyDiagnostics> = query
+        .iter()
+        .map(|(entity, transform, last_pos, mass, radius)| BodyDiagnostics {
+            entity,
+            position: transform.translation,
+            velocity: (transform.translation - last_pos.0) / timestep,
+            mass: mass.0,
+            radius: radius.0,
+            neighbors: neighbor_count(&grid, entity, transform.translation, density_color_settings.neighbor_radius),
+        })
+        .collect();
+
+    let path = Path::new(SNAPSHOT_DIR).join(format!("snapshot_{}.csv", ticks.0));
+    match fs::write(&path, diagnostics_csv(&mut rows)) {
+        Ok(()) => info!("Wrote {} bodies to {}.", rows.len(), path.display()),
+        Err(err) => error!("Failed to write diagnostics to {}: {err}", path.display()),
+    }
+}
+
+/// A system to replace the current bodies with the simulation state saved in the default file.
+#[allow(clippy::too_many_arguments)]
+fn load_on_key(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut params: ResMut<SimulationParams>,
+    mut physics_settings: ResMut<PhysicsSettings>,
+    body_meshes: Res<BodyMeshes>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    query: Query<Entity, With<Body>>,
+) {
+    if !key_input.just_pressed(key_bindings.load_state) {
+        return;
+    }
+
+    // Leave the current simulation untouched if the file can't be used.
+    let snapshot = match load_snapshot(Path::new(STATE_PATH)) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            error!("Failed to load the simulation state from {STATE_PATH}: {err}");
+            return;
+        }
+    };
+
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+    // The bodies are spawned for the settings they were saved with, which are restored below.
+    let timestep = snapshot.physics.substep(time.timestep()).as_secs_f32();
+    for body in &snapshot.bodies {
+        spawn_body(&mut commands, body_meshes.detailed(), &mut materials, &mut palette, body_descriptor(body, timestep));
+    }
+    *physics_settings = snapshot.physics;
+
+    // The bodies were just replaced directly, so bypass change detection to stop the
+    // body count from being matched again against bodies that haven't spawned yet.
+    let body_count = snapshot.bodies.len();
+    let mut loaded_params = snapshot.params;
+    loaded_params.body_count = body_count;
+    *params.bypass_change_detection() = loaded_params;
+    info!("Loaded {body_count} bodies from {STATE_PATH}.");
+}
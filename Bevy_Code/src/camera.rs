@@ -1,33 +1,175 @@
-use std::{f32::consts::FRAC_PI_2, ops::Range};
-use bevy::{input::mouse::{AccumulatedMouseMotion, MouseScrollUnit, MouseWheel}, math::ops::cbrt, prelude::*};
+use std::{f32::consts::{FRAC_PI_2, PI, TAU}, ops::Range};
+use bevy::{
+    core_pipeline::bloom::Bloom,
+    input::mouse::{AccumulatedMouseMotion, MouseScrollUnit, MouseWheel},
+    math::{ops::cbrt, StableInterpolate},
+    prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_egui::input::{egui_wants_any_pointer_input, EguiWantsInput};
+use serde::{Deserialize, Serialize};
+use crate::{
+    bindings::KeyBindings,
+    bodies::{LastPos, PhysicsSettings, SystemBounds},
+};
 
-/// Camera settings for development purposes, will not change during runtime.
+/// Steepest pitch the camera can reach, whatever the pitch range is set to. Straight up or
+/// down, yaw and roll turn about the same axis, so the yaw read back from the rotation stops
+/// meaning anything and mouse look jumps. Stopping just short keeps yaw well defined, at the
+/// cost of never looking exactly straight down.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.001;
+/// Steepest pitch mouse look reaches unless the config file says otherwise. Limiting pitch stops
+/// some unexpected rotation past 90 degrees up or down.
+const DEFAULT_PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+/// Yaw the top-down view is snapped to, so the map always has the same heading.
+const TOP_DOWN_YAW: f32 = 0.0;
+
+/// Orbit inertia stops once it has slowed below this many pixels of mouse motion a second.
+const ORBIT_INERTIA_MIN_SPEED: f32 = 5.0;
+/// How quickly the remembered drag speed follows the mouse, smoothing over frames without motion.
+const ORBIT_VELOCITY_SMOOTHING: f32 = 30.0;
+
+/// Pixel scroll is more precise, so this many pixels count as one line of scrolling.
+const PIXELS_PER_LINE: f32 = 10.0;
+
+/// Camera settings for development purposes, mostly left unchanged during runtime.
 #[derive(Debug, Resource)]
 struct CameraDevSettings {
     pub pitch_speed: f32,
+    pub invert_pitch: bool,
+    pub invert_yaw: bool,
     pub pitch_range: Range<f32>,
     pub yaw_speed: f32,
     pub zoom_speed: f32,
     pub zoom_range: Range<f32>,
     pub move_speed: f32,
     pub pan_speed: f32,
+    pub glide_speed: f32,
+    pub fly_speed_range: Range<f32>,
+    pub fly_speed_step: f32,
+    pub stick_look_sensitivity: f32,
+    pub stick_zoom_sensitivity: f32,
+    pub roll_speed: f32,
+    pub bloom_intensity: f32,
+    /// Whether the orbit keeps turning after the drag is released.
+    pub orbit_inertia: bool,
+    /// How quickly orbit inertia dies away, as an exponential decay rate per second.
+    pub orbit_inertia_decay: f32,
+    /// Whether a followed body is tracked between physics ticks rather than jumping with each one.
+    /// The target then trails the body by up to a tick, but moves every frame.
+    pub smooth_follow: bool,
+}
+
+/// Camera preferences read from the config file, alongside the simulation parameters. Inserted
+/// before `CameraPlugin`, which starts the camera with them. Preferences missing from the file
+/// keep their default.
+#[derive(Debug, Clone, Resource, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    pub invert_pitch: bool,
+    pub invert_yaw: bool,
+    /// Steepest the camera can be turned to look up or down, in radians. Anything past `MAX_PITCH`
+    /// is held there.
+    pub pitch_limit: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            invert_pitch: false,
+            invert_yaw: false,
+            pitch_limit: DEFAULT_PITCH_LIMIT,
+        }
+    }
 }
 
 /// Camera settings that can be modified during runtime.
 #[derive(Debug, Resource)]
-struct CameraSettings {
+pub struct CameraSettings {
     pub orbit_distance: f32,
     pub target: Vec3,
+    pub fly_speed: f32,
+    pub roll: f32,
+}
+
+/// Whether the camera orbits a target or flies freely through the scene.
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CameraMode {
+    #[default]
+    Orbit,
+    FreeFly,
+}
+
+/// A complete description of an orbit camera view.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CameraView {
+    pub target: Vec3,
+    pub orbit_distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    #[serde(default)]
+    pub roll: f32,
+}
+
+/// How fast the orbit was last being dragged, in pixels of mouse motion a second.
+#[derive(Debug, Default, Resource)]
+struct OrbitInertia(Vec2);
+
+/// A view the camera is currently gliding towards, if any.
+#[derive(Debug, Default, Resource)]
+pub struct CameraGlide(pub Option<CameraView>);
+
+/// The body the orbit camera is following, if any, keeping it as the target as it moves.
+#[derive(Debug, Default, Resource)]
+pub struct CameraFollow(pub Option<Entity>);
+
+/// The stages the camera is updated in each frame. Orbiting places the camera from the target
+/// and orbit distance, so everything that changes those has to run first for the camera to land
+/// where they say in the same frame, rather than a frame behind.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CameraSet {
+    /// Reading input and glides into the target, orbit distance, and roll.
+    Input,
+    /// Moving the camera itself, from the settings left by the input.
+    Apply,
 }
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
+        let camera_config = app.world().get_resource::<CameraConfig>().cloned().unwrap_or_default();
         app.insert_resource(CameraSettings::default())
-            .insert_resource(CameraDevSettings::default())
-            .add_systems(Startup, (setup_camera, setup_ambient_light))
-            .add_systems(Update, (orbit, zoom, move_camera, pan_camera));
+            .insert_resource(CameraDevSettings::from_config(&camera_config))
+            .init_resource::<CameraGlide>()
+            .init_resource::<CameraFollow>()
+            .init_resource::<OrbitInertia>()
+            .init_state::<CameraMode>()
+            .configure_sets(Update, (CameraSet::Input, CameraSet::Apply).chain())
+            .add_systems(Startup, setup_camera)
+            .add_systems(Update, (toggle_camera_mode, toggle_look_inversion, toggle_bloom))
+            .add_systems(Update, roll_camera.after(glide).in_set(CameraSet::Input))
+            // Following runs after anything else that moves the target, so the body stays centered.
+            .add_systems(Update, follow_body.after(glide).after(move_camera).after(pan_camera).after(gamepad_control)
+                .in_set(CameraSet::Input)
+                .run_if(in_state(CameraMode::Orbit)))
+            .add_systems(Update, (glide, gamepad_control, top_down_view, frame_system, move_camera)
+                .in_set(CameraSet::Input)
+                .run_if(in_state(CameraMode::Orbit)))
+            // The mouse is left to the control panel while it is over it.
+            .add_systems(Update, (zoom, pan_camera)
+                .in_set(CameraSet::Input)
+                .run_if(in_state(CameraMode::Orbit))
+                .run_if(not(egui_wants_any_pointer_input)))
+            .add_systems(Update, orbit
+                .in_set(CameraSet::Apply)
+                .run_if(in_state(CameraMode::Orbit)))
+            .add_systems(Update, fly_speed.in_set(CameraSet::Input).run_if(in_state(CameraMode::FreeFly)))
+            .add_systems(Update, (fly_look, fly_move)
+                .in_set(CameraSet::Apply)
+                .run_if(in_state(CameraMode::FreeFly)))
+            .add_systems(OnEnter(CameraMode::FreeFly), grab_cursor)
+            .add_systems(OnExit(CameraMode::FreeFly), leave_free_fly);
     }
 }
 
@@ -36,74 +178,318 @@ impl Default for CameraSettings {
         Self {
             orbit_distance: 20.0,
             target: Vec3::ZERO,
+            fly_speed: 10.0,
+            roll: 0.0,
         }
     }
 }
 
 impl Default for CameraDevSettings {
     fn default() -> Self {
-        // Limiting pitch stops some unexpected rotation past 90 degress up or down.
-        let pitch_limit = FRAC_PI_2 - 0.01;
+        // The config file can raise this as far as MAX_PITCH to look more steeply up or down.
+        let pitch_limit = DEFAULT_PITCH_LIMIT;
         Self {
             pitch_speed: 0.0015,
+            invert_pitch: false,
+            invert_yaw: false,
             pitch_range: -pitch_limit..pitch_limit,
             yaw_speed: 0.002,
             zoom_speed: 10.0,
             zoom_range: 5.0..100.0,
             move_speed: 10.,
             pan_speed: 0.5,
+            glide_speed: 4.0,
+            fly_speed_range: 1.0..200.0,
+            fly_speed_step: 1.2,
+            // Full stick deflection turns as fast as moving the mouse this many pixels a second.
+            stick_look_sensitivity: 800.0,
+            // Fully pressed triggers zoom as fast as this many scroll lines a second.
+            stick_zoom_sensitivity: 4.0,
+            roll_speed: 1.0,
+            bloom_intensity: 0.2,
+            orbit_inertia: true,
+            orbit_inertia_decay: 4.0,
+            smooth_follow: false,
+        }
+    }
+}
+
+impl CameraDevSettings {
+    /// The default settings, with the preferences from the config file in place.
+    fn from_config(camera_config: &CameraConfig) -> Self {
+        // Clamping would pass NaN straight through, leaving a range that rejects every pitch.
+        let pitch_limit = if camera_config.pitch_limit.is_finite() {
+            camera_config.pitch_limit.clamp(0.0, MAX_PITCH)
+        } else {
+            DEFAULT_PITCH_LIMIT
+        };
+        Self {
+            invert_pitch: camera_config.invert_pitch,
+            invert_yaw: camera_config.invert_yaw,
+            pitch_range: -pitch_limit..pitch_limit,
+            ..default()
+        }
+    }
+}
+
+impl CameraView {
+    /// Captures the view the camera is currently showing.
+    pub fn capture(camera_settings: &CameraSettings, camera_transform: &Transform) -> Self {
+        let (yaw, pitch, _) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+        Self {
+            target: camera_settings.target,
+            orbit_distance: camera_settings.orbit_distance,
+            yaw,
+            pitch,
+            roll: camera_settings.roll,
         }
     }
+
+    /// The camera rotation described by this view.
+    pub fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, self.roll)
+    }
 }
 
-/// A function to increase brightness of the scene.
-fn setup_ambient_light(mut ambient_light: ResMut<AmbientLight>) {
-    println!("Setting up ambient light for the scene.");
-    ambient_light.brightness = 500.0;
+/// A function to wrap an angle into the range -PI..PI.
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
 }
 
 /// A system to spawn a camera with default settings.
 fn setup_camera(
     mut commands: Commands,
-    camera_settings: Res<CameraSettings>
+    camera_settings: Res<CameraSettings>,
+    camera_dev_settings: Res<CameraDevSettings>,
 ) {
     commands.spawn((
         Name::new("Camera"),    // dev note: might not be necessary to have a name.
         Camera3d::default(),
+        // Bloom needs HDR so emissive bodies can be brighter than white.
+        Camera {
+            hdr: true,
+            ..default()
+        },
+        bloom(&camera_dev_settings),
         Transform::from_xyz(camera_settings.orbit_distance, 0.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y),
     ));
 }
 
+/// A function to rotate the camera by a mouse motion, keeping the pitch within its limits.
+fn look(rotation: Quat, delta: Vec2, camera_dev_settings: &CameraDevSettings) -> Quat {
+    // No need to multiply by delta time as Accumulated Mouse Motion already accounts for it.
+    let mut delta_pitch = delta.y * camera_dev_settings.pitch_speed;
+    let mut delta_yaw = delta.x * camera_dev_settings.yaw_speed;
+    if camera_dev_settings.invert_pitch {
+        delta_pitch = -delta_pitch;
+    }
+    if camera_dev_settings.invert_yaw {
+        delta_yaw = -delta_yaw;
+    }
+
+    // Obtain the existing pitch, yaw, and roll values from the rotation.
+    let (yaw, pitch, roll) = rotation.to_euler(EulerRot::YXZ);
+
+    // Establish the new yaw and pitch, preventing them from exceeding our limits.
+    // Roll is applied last in this order, so keeping it doesn't affect the pitch clamp.
+    let pitch = (pitch - delta_pitch).clamp(
+        camera_dev_settings.pitch_range.start.max(-MAX_PITCH),
+        camera_dev_settings.pitch_range.end.min(MAX_PITCH),
+    );
+    let yaw = yaw - delta_yaw;
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, roll)
+}
+
+/// A function to turn the orbit camera by a mouse motion, returning its new rotation and
+/// the translation that keeps it looking at the target from the orbit distance.
+fn orbit_step(
+    rotation: Quat,
+    delta: Vec2,
+    target: Vec3,
+    orbit_distance: f32,
+    camera_dev_settings: &CameraDevSettings,
+) -> (Quat, Vec3) {
+    // Skip the euler round trip when not turning, so a still camera doesn't drift.
+    let rotation = if delta == Vec2::ZERO {
+        rotation
+    } else {
+        look(rotation, delta, camera_dev_settings)
+    };
+
+    // Adjust the translation to maintain the correct orientation toward the orbit target.
+    (rotation, target - rotation * Vec3::NEG_Z * orbit_distance)
+}
+
+/// A function to slow down orbit inertia, stopping it once it is too slow to notice.
+fn coast(velocity: Vec2, dt: f32, camera_dev_settings: &CameraDevSettings) -> Vec2 {
+    let velocity = velocity * ops::exp(-camera_dev_settings.orbit_inertia_decay * dt);
+    if velocity.length() < ORBIT_INERTIA_MIN_SPEED {
+        Vec2::ZERO
+    } else {
+        velocity
+    }
+}
+
 /// A systen to orbit the camera around a point dependent on orbit distance.
+#[allow(clippy::too_many_arguments)]
 fn orbit(
     mut camera_transform: Single<&mut Transform, With<Camera>>,
     camera_dev_settings: Res<CameraDevSettings>,
     camera_settings: Res<CameraSettings>,
+    camera_glide: Res<CameraGlide>,
+    mut orbit_inertia: ResMut<OrbitInertia>,
     mouse_motion: Res<AccumulatedMouseMotion>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    key_bindings: Res<KeyBindings>,
+    egui_wants_input: Res<EguiWantsInput>,
+    time: Res<Time>,
 ) {
-    if mouse_input.pressed(MouseButton::Right) {
-        let delta = mouse_motion.delta;
+    let dt = time.delta_secs();
+    // The mouse is left to the control panel while it is over it, but the camera still has to
+    // keep up with the target, so only the drag is ignored.
+    let dragging = mouse_input.pressed(key_bindings.orbit_button) && !egui_wants_input.wants_any_pointer_input();
+    let delta = if dragging {
+        // Remember how fast the drag is going, so it can carry on at that speed once released.
+        if dt > 0.0 {
+            orbit_inertia.0.smooth_nudge(&(mouse_motion.delta / dt), ORBIT_VELOCITY_SMOOTHING, dt);
+        }
+        mouse_motion.delta
+    } else if camera_dev_settings.orbit_inertia && camera_glide.0.is_none() {
+        // Coasting goes through the same step as dragging, so the translation still follows the target.
+        orbit_inertia.0 = coast(orbit_inertia.0, dt, &camera_dev_settings);
+        orbit_inertia.0 * dt
+    } else {
+        // Inertia is dropped when off, or when a glide takes over the rotation, so they never fight.
+        orbit_inertia.0 = Vec2::ZERO;
+        Vec2::ZERO
+    };
 
-        // No need to multiply by delta time as Accumulated Mouse Motion already accounts for it.
-        let delta_pitch = delta.y * camera_dev_settings.pitch_speed;
-        let delta_yaw = delta.x * camera_dev_settings.yaw_speed;
+    let (rotation, translation) = orbit_step(
+        camera_transform.rotation,
+        delta,
+        camera_settings.target,
+        camera_settings.orbit_distance,
+        &camera_dev_settings,
+    );
+    camera_transform.rotation = rotation;
+    camera_transform.translation = translation;
+}
 
-        // Obtain the existing pitch, yaw, and roll values from the transform.
-        let (yaw, pitch, _) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+/// A system to smoothly move the camera towards the view stored in `CameraGlide`.
+fn glide(
+    mut camera_glide: ResMut<CameraGlide>,
+    mut camera_transform: Single<&mut Transform, With<Camera>>,
+    mut camera_settings: ResMut<CameraSettings>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    key_bindings: Res<KeyBindings>,
+    time: Res<Time>,
+) {
+    let Some(view) = camera_glide.0 else {
+        return;
+    };
 
-        // Establish the new yaw and pitch, preventing them from exceeding our limits.
-        let pitch = (pitch - delta_pitch).clamp(
-            camera_dev_settings.pitch_range.start,
-            camera_dev_settings.pitch_range.end,
-        );
-        let yaw = yaw - delta_yaw;
-        camera_transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+    // Orbiting by hand cancels the glide rather than fighting it.
+    if mouse_input.pressed(key_bindings.orbit_button) {
+        camera_glide.0 = None;
+        return;
     }
 
-    // Adjust the translation to maintain the correct orientation toward the orbit target.
-    let target = camera_settings.target;
-    camera_transform.translation = target - camera_transform.forward() * camera_settings.orbit_distance;
+    // Exponentially approach the view so the glide eases out as it arrives.
+    let decay_rate = camera_dev_settings.glide_speed;
+    let dt = time.delta_secs();
+    let rotation = view.rotation();
+    camera_settings.target.smooth_nudge(&view.target, decay_rate, dt);
+    camera_settings.orbit_distance.smooth_nudge(&view.orbit_distance, decay_rate, dt);
+    camera_transform.rotation.smooth_nudge(&rotation, decay_rate, dt);
+
+    // Roll the short way around so a glide never spins through a full turn.
+    let roll_difference = wrap_angle(view.roll - camera_settings.roll);
+    camera_settings.roll = wrap_angle(camera_settings.roll + roll_difference * (1.0 - ops::exp(-decay_rate * dt)));
+
+    // Snap into place once the remaining difference is no longer visible.
+    if camera_settings.target.distance(view.target) < 0.01
+        && (camera_settings.orbit_distance - view.orbit_distance).abs() < 0.01
+        && camera_transform.rotation.angle_between(rotation) < 0.001
+    {
+        camera_settings.target = view.target;
+        camera_settings.orbit_distance = view.orbit_distance;
+        camera_settings.roll = view.roll;
+        camera_transform.rotation = rotation;
+        camera_glide.0 = None;
+    }
+}
+
+/// A system to keep the orbit target on the body being followed, so orbiting and zooming happen
+/// around it as it moves. Following stops when the stop following key is pressed, or when the
+/// body is gone, having merged, shattered, or escaped.
+#[allow(clippy::too_many_arguments)]
+fn follow_body(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut camera_follow: ResMut<CameraFollow>,
+    mut camera_settings: ResMut<CameraSettings>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    physics_settings: Res<PhysicsSettings>,
+    fixed_time: Res<Time<Fixed>>,
+    bodies: Query<(&GlobalTransform, Option<&LastPos>)>,
+) {
+    let Some(entity) = camera_follow.0 else {
+        return;
+    };
+    if key_input.just_pressed(key_bindings.stop_following) {
+        camera_follow.0 = None;
+        return;
+    }
+
+    let Ok((transform, last_pos)) = bodies.get(entity) else {
+        info!("Stopped following body {entity}, it no longer exists.");
+        camera_follow.0 = None;
+        return;
+    };
+    camera_settings.target = match last_pos {
+        Some(last_pos) if camera_dev_settings.smooth_follow => interpolate_tick(
+            transform.translation(),
+            last_pos.0,
+            physics_settings.substeps.max(1),
+            fixed_time.overstep_fraction(),
+        ),
+        _ => transform.translation(),
+    };
+}
+
+/// A function to find where a body was part way through the last physics tick, going back from
+/// where it is now along its velocity. Last positions are only a substep behind, so the step is
+/// scaled up to a whole tick.
+fn interpolate_tick(position: Vec3, last_pos: Vec3, substeps: u32, fraction: f32) -> Vec3 {
+    let tick_start = position - (position - last_pos) * substeps as f32;
+    tick_start.lerp(position, fraction)
+}
+
+/// A system to roll the camera about its view direction, or reset its roll.
+fn roll_camera(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut camera_transform: Single<&mut Transform, With<Camera>>,
+    mut camera_settings: ResMut<CameraSettings>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    time: Res<Time>,
+) {
+    let roll_step = camera_dev_settings.roll_speed * time.delta_secs();
+    if key_input.pressed(key_bindings.roll_left) {
+        camera_settings.roll += roll_step;
+    }
+    if key_input.pressed(key_bindings.roll_right) {
+        camera_settings.roll -= roll_step;
+    }
+    if key_input.just_pressed(key_bindings.reset_roll) {
+        camera_settings.roll = 0.0;
+    }
+    camera_settings.roll = wrap_angle(camera_settings.roll);
+
+    // Only the roll is replaced, so yaw, pitch, and the orbit translation are left untouched.
+    let (yaw, pitch, _) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+    camera_transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, camera_settings.roll);
 }
 
 /// A system to change the orbit distance based on mouse wheel input.
@@ -114,33 +500,74 @@ fn zoom(
 ) {
     // Iterate through mouse wheel inputs and update the orbit distance accordingly.
     for ev in evr_scroll.read() {
+        camera_settings.orbit_distance = zoom_step(camera_settings.orbit_distance, ev.y, ev.unit, &camera_dev_settings);
+    }
+}
 
-        // Calculate the orbit distance as a value between 0.1 and 1 relative to the zoom range.
-        let mut dist_modifier = camera_settings.orbit_distance / 
-            (camera_dev_settings.zoom_range.end - camera_dev_settings.zoom_range.start);
-        dist_modifier = dist_modifier.clamp(0.1, 1.0);
-
-        // Adjust the orbit distance based on the scroll input and distance modifier.
-        match ev.unit {
-            MouseScrollUnit::Line =>{
-                camera_settings.orbit_distance -= ev.y * camera_dev_settings.zoom_speed * dist_modifier;
-            }
-            // Pixel scroll is more precise, so we divide by 10 to make it less sensitive.
-            MouseScrollUnit::Pixel => {
-                camera_settings.orbit_distance -= ev.y * camera_dev_settings.zoom_speed * dist_modifier / 10.0;
-            }
-        }
-        // Clamp the orbit distance to the defined zoom range.
-        camera_settings.orbit_distance = camera_settings.orbit_distance.clamp(
-            camera_dev_settings.zoom_range.start,
-            camera_dev_settings.zoom_range.end,
-        );
+/// A function to zoom the orbit distance by a mouse wheel scroll, staying within the zoom range.
+fn zoom_step(current: f32, scroll_y: f32, unit: MouseScrollUnit, camera_dev_settings: &CameraDevSettings) -> f32 {
+    let lines = match unit {
+        MouseScrollUnit::Line => scroll_y,
+        MouseScrollUnit::Pixel => scroll_y / PIXELS_PER_LINE,
+    };
+    zoom_orbit_distance(current, lines, camera_dev_settings)
+}
+
+/// A function to zoom the orbit distance in by a number of scroll lines, staying within the zoom range.
+fn zoom_orbit_distance(orbit_distance: f32, lines: f32, camera_dev_settings: &CameraDevSettings) -> f32 {
+    // Calculate the orbit distance as a value between 0.1 and 1 relative to the zoom range.
+    let mut dist_modifier = orbit_distance /
+        (camera_dev_settings.zoom_range.end - camera_dev_settings.zoom_range.start);
+    dist_modifier = dist_modifier.clamp(0.1, 1.0);
+
+    // Adjust the orbit distance based on the scroll input and distance modifier,
+    // then clamp it to the defined zoom range.
+    (orbit_distance - lines * camera_dev_settings.zoom_speed * dist_modifier).clamp(
+        camera_dev_settings.zoom_range.start,
+        camera_dev_settings.zoom_range.end,
+    )
+}
+
+/// A system to orbit, move, and zoom the camera with the first connected gamepad.
+fn gamepad_control(
+    gamepads: Query<&Gamepad>,
+    mut camera_transform: Single<&mut Transform, With<Camera>>,
+    mut camera_settings: ResMut<CameraSettings>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    time: Res<Time>,
+) {
+    // Without a connected gamepad the mouse and keyboard are the only controls.
+    let Some(gamepad) = gamepads.iter().next() else {
+        return;
+    };
+    let dt = time.delta_secs();
+
+    // The right stick orbits like dragging the mouse, with stick up matching mouse up.
+    let look_delta = gamepad.right_stick() * Vec2::new(1.0, -1.0)
+        * camera_dev_settings.stick_look_sensitivity * dt;
+    if look_delta != Vec2::ZERO {
+        camera_transform.rotation = look(camera_transform.rotation, look_delta, &camera_dev_settings);
+    }
+
+    // The left stick moves the target along the camera's local axes, like the movement keys.
+    let stick = gamepad.left_stick();
+    let movement = stick.x * *camera_transform.local_x() - stick.y * *camera_transform.local_z();
+    camera_settings.target += movement * dt * camera_dev_settings.move_speed
+        * cbrt(camera_settings.orbit_distance);
+
+    // The right trigger zooms in and the left trigger zooms out.
+    let zoom_input = gamepad.get(GamepadButton::RightTrigger2).unwrap_or(0.0)
+        - gamepad.get(GamepadButton::LeftTrigger2).unwrap_or(0.0);
+    if zoom_input != 0.0 {
+        let lines = zoom_input * camera_dev_settings.stick_zoom_sensitivity * dt;
+        camera_settings.orbit_distance = zoom_orbit_distance(camera_settings.orbit_distance, lines, &camera_dev_settings);
     }
 }
 
 /// A system to update the camera's target position based on button input.
 fn move_camera(
     key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
     mut camera_settings: ResMut<CameraSettings>,
     camera_dev_settings: Res<CameraDevSettings>,
     camera_transform: Single<&Transform, With<Camera>>,
@@ -149,22 +576,22 @@ fn move_camera(
     let mut movement = Vec3::ZERO;
 
     // Update movement vector based on inputs.
-    if key_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+    if key_input.any_pressed(key_bindings.move_left.iter().copied()) {
         movement -= *camera_transform.local_x(); // Move left.
     }
-    if key_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+    if key_input.any_pressed(key_bindings.move_right.iter().copied()) {
         movement += *camera_transform.local_x(); // Move right.
     }
-    if key_input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) {
+    if key_input.any_pressed(key_bindings.move_forward.iter().copied()) {
         movement -= *camera_transform.local_z(); // Move forward.
     }
-    if key_input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) {
+    if key_input.any_pressed(key_bindings.move_back.iter().copied()) {
         movement += *camera_transform.local_z(); // Move backward.
     }
-    if key_input.any_pressed([KeyCode::Space, KeyCode::Enter]) {
+    if key_input.any_pressed(key_bindings.move_up.iter().copied()) {
         movement += *camera_transform.local_y(); // Move up.
     }
-    if key_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+    if key_input.any_pressed(key_bindings.move_down.iter().copied()) {
         movement -= *camera_transform.local_y(); // Move down.
     }
 
@@ -180,12 +607,13 @@ fn move_camera(
 fn pan_camera(
     mouse_motion: Res<AccumulatedMouseMotion>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    key_bindings: Res<KeyBindings>,
     mut camera_settings: ResMut<CameraSettings>,
     camera_dev_settings: Res<CameraDevSettings>,
     camera_transform: Single<&Transform, With<Camera>>,
     time: Res<Time>,
 ) {
-    if mouse_input.pressed(MouseButton::Left) {
+    if mouse_input.pressed(key_bindings.pan_button) {
         let delta = mouse_motion.delta;
 
         // Calculate the movement vector based on the camera's local axes.
@@ -196,4 +624,432 @@ fn pan_camera(
         // Scale movement vector by delta time and pan speed, then apply to the camera target.
         camera_settings.target += movement * camera_dev_settings.pan_speed * time.delta_secs();
     }
-}
\ No newline at end of file
+}
+
+/// A system to switch between the orbit and free-fly camera modes.
+fn toggle_camera_mode(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    camera_mode: Res<State<CameraMode>>,
+    mut next_camera_mode: ResMut<NextState<CameraMode>>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_free_fly) {
+        next_camera_mode.set(match camera_mode.get() {
+            CameraMode::Orbit => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::Orbit,
+        });
+    }
+}
+
+/// A system to snap the orbit camera to look straight down on the target, like a map.
+fn top_down_view(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut camera_transform: Single<&mut Transform, With<Camera>>,
+    mut camera_settings: ResMut<CameraSettings>,
+    mut camera_glide: ResMut<CameraGlide>,
+    mut orbit_inertia: ResMut<OrbitInertia>,
+) {
+    if !key_input.just_pressed(key_bindings.top_down_view) {
+        return;
+    }
+
+    // As close to straight down as yaw stays well defined, whatever the pitch range. The first
+    // mouse look afterwards brings the pitch back within the range.
+    camera_transform.rotation = Quat::from_euler(EulerRot::YXZ, TOP_DOWN_YAW, -MAX_PITCH, 0.0);
+    camera_settings.roll = 0.0;
+    // A snap replaces any glide or coasting that was under way.
+    camera_glide.0 = None;
+    orbit_inertia.0 = Vec2::ZERO;
+}
+
+/// A system to center the orbit camera on the bodies and set its distance so all of them fit in view.
+fn frame_system(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    system_bounds: Res<SystemBounds>,
+    mut camera_settings: ResMut<CameraSettings>,
+    mut camera_glide: ResMut<CameraGlide>,
+    mut camera_follow: ResMut<CameraFollow>,
+    projection: Single<&Projection, With<Camera>>,
+) {
+    if !key_input.just_pressed(key_bindings.frame_system) {
+        return;
+    }
+
+    camera_settings.target = system_bounds.center;
+    camera_settings.orbit_distance = framing_distance(system_bounds.radius, &projection);
+    // Framing replaces wherever the camera was headed or whatever it was following.
+    camera_glide.0 = None;
+    camera_follow.0 = None;
+}
+
+/// A function to find how far away the camera has to be for a sphere of the radius to fit in view,
+/// in whichever of the width and height of the view is narrower.
+fn framing_distance(radius: f32, projection: &Projection) -> f32 {
+    let half_angle = match projection {
+        Projection::Perspective(perspective) => {
+            let half_fov = perspective.fov / 2.0;
+            half_fov.min((half_fov.tan() * perspective.aspect_ratio).atan())
+        }
+        // An orthographic view is the same size at any distance, so it only has to be outside the sphere.
+        _ => FRAC_PI_2,
+    };
+    radius / half_angle.sin()
+}
+
+/// A system to toggle inverted vertical and horizontal mouse look.
+fn toggle_look_inversion(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut camera_dev_settings: ResMut<CameraDevSettings>,
+) {
+    if key_input.just_pressed(key_bindings.invert_pitch) {
+        camera_dev_settings.invert_pitch = !camera_dev_settings.invert_pitch;
+        info!("Inverted pitch: {}", camera_dev_settings.invert_pitch);
+    }
+    if key_input.just_pressed(key_bindings.invert_yaw) {
+        camera_dev_settings.invert_yaw = !camera_dev_settings.invert_yaw;
+        info!("Inverted yaw: {}", camera_dev_settings.invert_yaw);
+    }
+}
+
+/// A function to build the bloom settings for the camera.
+fn bloom(camera_dev_settings: &CameraDevSettings) -> Bloom {
+    // The natural preset conserves energy, so only emissive bodies bright enough to exceed
+    // white glow noticeably, while lit bodies keep their colors against the black background.
+    Bloom {
+        intensity: camera_dev_settings.bloom_intensity,
+        ..Bloom::NATURAL
+    }
+}
+
+/// A system to turn bloom on and off.
+fn toggle_bloom(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    camera: Single<(Entity, Has<Bloom>), With<Camera>>,
+) {
+    if !key_input.just_pressed(key_bindings.toggle_bloom) {
+        return;
+    }
+
+    let (entity, has_bloom) = *camera;
+    if has_bloom {
+        commands.entity(entity).remove::<Bloom>();
+    } else {
+        commands.entity(entity).insert(bloom(&camera_dev_settings));
+    }
+    info!("Bloom: {}", !has_bloom);
+}
+
+/// A system to lock and hide the cursor so the mouse can look around freely.
+fn grab_cursor(mut window: Single<&mut Window, With<PrimaryWindow>>) {
+    window.cursor_options.grab_mode = CursorGrabMode::Locked;
+    window.cursor_options.visible = false;
+}
+
+/// A system to release the cursor and re-center the orbit on the point the camera is looking at.
+fn leave_free_fly(
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut camera_settings: ResMut<CameraSettings>,
+    camera_transform: Single<&Transform, With<Camera>>,
+) {
+    window.cursor_options.grab_mode = CursorGrabMode::None;
+    window.cursor_options.visible = true;
+
+    // Orbiting this point at the current orbit distance leaves the camera where it is.
+    camera_settings.target = camera_transform.translation + camera_transform.forward() * camera_settings.orbit_distance;
+}
+
+/// A system to rotate the free-fly camera with the mouse, without needing a button held.
+fn fly_look(
+    mut camera_transform: Single<&mut Transform, With<Camera>>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+) {
+    camera_transform.rotation = look(camera_transform.rotation, mouse_motion.delta, &camera_dev_settings);
+}
+
+/// A system to change the free-fly speed based on mouse wheel input.
+fn fly_speed(
+    mut evr_scroll: EventReader<MouseWheel>,
+    camera_dev_settings: Res<CameraDevSettings>,
+    mut camera_settings: ResMut<CameraSettings>,
+) {
+    for ev in evr_scroll.read() {
+        // Pixel scroll is more precise, so it is scaled down as in zoom.
+        let steps = match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / PIXELS_PER_LINE,
+        };
+
+        // Scale the speed by a constant factor per step so it feels the same at any speed.
+        camera_settings.fly_speed = (camera_settings.fly_speed * camera_dev_settings.fly_speed_step.powf(steps)).clamp(
+            camera_dev_settings.fly_speed_range.start,
+            camera_dev_settings.fly_speed_range.end,
+        );
+    }
+}
+
+/// A system to move the free-fly camera along its local axes based on button input.
+fn fly_move(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    camera_settings: Res<CameraSettings>,
+    mut camera_transform: Single<&mut Transform, With<Camera>>,
+    time: Res<Time>,
+) {
+    let mut movement = Vec3::ZERO;
+
+    // Update movement vector based on inputs.
+    if key_input.any_pressed(key_bindings.move_left.iter().copied()) {
+        movement -= *camera_transform.local_x(); // Move left.
+    }
+    if key_input.any_pressed(key_bindings.move_right.iter().copied()) {
+        movement += *camera_transform.local_x(); // Move right.
+    }
+    if key_input.any_pressed(key_bindings.move_forward.iter().copied()) {
+        movement -= *camera_transform.local_z(); // Move forward.
+    }
+    if key_input.any_pressed(key_bindings.move_back.iter().copied()) {
+        movement += *camera_transform.local_z(); // Move backward.
+    }
+    if key_input.any_pressed(key_bindings.move_up.iter().copied()) {
+        movement += *camera_transform.local_y(); // Move up.
+    }
+    if key_input.any_pressed(key_bindings.move_down.iter().copied()) {
+        movement -= *camera_transform.local_y(); // Move down.
+    }
+
+    // Normalize movement and scale by delta time and fly speed.
+    camera_transform.translation += movement.normalize_or_zero() * time.delta_secs() * camera_settings.fly_speed;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::{input::{mouse::MouseMotion, InputPlugin}, state::app::StatesPlugin, time::TimeUpdateStrategy};
+    use bevy_egui::input::EguiWantsInput;
+    use super::*;
+
+    #[test]
+    fn panning_moves_the_camera_in_the_same_frame() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, StatesPlugin))
+            .init_resource::<KeyBindings>()
+            .init_resource::<EguiWantsInput>()
+            .init_resource::<PhysicsSettings>()
+            .init_resource::<SystemBounds>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / 60.0)))
+            .add_plugins(CameraPlugin);
+        // The first update spawns the camera, and the second gives time a delta to move it by.
+        app.update();
+        app.update();
+
+        let camera_translation = |app: &mut App| {
+            app.world_mut().query_filtered::<&Transform, With<Camera>>().single(app.world()).unwrap().translation
+        };
+        let target = app.world().resource::<CameraSettings>().target;
+        let before = camera_translation(&mut app);
+
+        let pan_button = app.world().resource::<KeyBindings>().pan_button;
+        app.world_mut().resource_mut::<ButtonInput<MouseButton>>().press(pan_button);
+        app.world_mut().send_event(MouseMotion { delta: Vec2::new(40.0, -25.0) });
+        app.update();
+
+        // The camera moves by exactly as much as the target, rather than catching up a frame later.
+        let moved = app.world().resource::<CameraSettings>().target - target;
+        assert!(moved.length() > 0.0);
+        assert!((camera_translation(&mut app) - (before + moved)).length() < 1e-4);
+    }
+
+    #[test]
+    fn config_files_carry_camera_preferences() {
+        // The same file holds the simulation parameters, which the camera preferences skip over.
+        let contents = "(body_count: 40, invert_pitch: true)";
+        let camera_config: CameraConfig = ron::from_str(contents).unwrap();
+        let params: crate::bodies::SimulationParams = ron::from_str(contents).unwrap();
+        assert_eq!(params.body_count, 40);
+
+        let camera_dev_settings = CameraDevSettings::from_config(&camera_config);
+        assert!(camera_dev_settings.invert_pitch);
+        assert!(!camera_dev_settings.invert_yaw);
+        assert_eq!(camera_dev_settings.pitch_range, -DEFAULT_PITCH_LIMIT..DEFAULT_PITCH_LIMIT);
+
+        // The pitch limit can be raised, but never past where yaw stops meaning anything.
+        let steep: CameraConfig = ron::from_str("(pitch_limit: 1.5)").unwrap();
+        assert_eq!(CameraDevSettings::from_config(&steep).pitch_range, -1.5..1.5);
+        let too_steep: CameraConfig = ron::from_str("(pitch_limit: 3.0)").unwrap();
+        assert_eq!(CameraDevSettings::from_config(&too_steep).pitch_range, -MAX_PITCH..MAX_PITCH);
+        let not_a_number = CameraConfig { pitch_limit: f32::NAN, ..default() };
+        assert_eq!(CameraDevSettings::from_config(&not_a_number).pitch_range, -DEFAULT_PITCH_LIMIT..DEFAULT_PITCH_LIMIT);
+    }
+
+    #[test]
+    fn following_tracks_the_body_until_it_is_gone() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<KeyBindings>();
+        world.init_resource::<CameraSettings>();
+        world.init_resource::<CameraDevSettings>();
+        world.init_resource::<PhysicsSettings>();
+        world.init_resource::<Time<Fixed>>();
+        let body = world.spawn(GlobalTransform::from_translation(Vec3::new(3.0, -1.0, 4.0))).id();
+        world.insert_resource(CameraFollow(Some(body)));
+
+        world.run_system_cached(follow_body).unwrap();
+        assert_eq!(world.resource::<CameraSettings>().target, Vec3::new(3.0, -1.0, 4.0));
+
+        // The target keeps up as the body moves.
+        *world.get_mut::<GlobalTransform>(body).unwrap() = GlobalTransform::from_translation(Vec3::new(5.0, 0.0, 2.0));
+        world.run_system_cached(follow_body).unwrap();
+        assert_eq!(world.resource::<CameraSettings>().target, Vec3::new(5.0, 0.0, 2.0));
+
+        // Once the body is despawned, the camera stays where it was and stops following.
+        world.despawn(body);
+        world.run_system_cached(follow_body).unwrap();
+        assert_eq!(world.resource::<CameraFollow>().0, None);
+        assert_eq!(world.resource::<CameraSettings>().target, Vec3::new(5.0, 0.0, 2.0));
+
+        // The stop following key lets go of a body that is still around.
+        let body = world.spawn(GlobalTransform::IDENTITY).id();
+        world.insert_resource(CameraFollow(Some(body)));
+        world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Escape);
+        world.run_system_cached(follow_body).unwrap();
+        assert_eq!(world.resource::<CameraFollow>().0, None);
+    }
+
+    #[test]
+    fn framing_fits_the_sphere_in_the_narrower_direction() {
+        let wide = Projection::Perspective(PerspectiveProjection { fov: FRAC_PI_2, aspect_ratio: 2.0, ..default() });
+        assert!((framing_distance(10.0, &wide) - 10.0 * 2.0f32.sqrt()).abs() < 1e-4);
+
+        // A tall view is narrower across, so the camera has to back off further.
+        let tall = Projection::Perspective(PerspectiveProjection { fov: FRAC_PI_2, aspect_ratio: 0.5, ..default() });
+        let half_width = 0.5f32.atan();
+        assert!((framing_distance(10.0, &tall) - 10.0 / half_width.sin()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn smooth_following_lags_the_body_by_the_leftover_tick() {
+        // Two substeps of 0.5 each make a tick of 1 along X.
+        let position = Vec3::new(4.0, 2.0, 0.0);
+        let last_pos = Vec3::new(3.5, 2.0, 0.0);
+        assert_eq!(interpolate_tick(position, last_pos, 2, 0.0), Vec3::new(3.0, 2.0, 0.0));
+        assert_eq!(interpolate_tick(position, last_pos, 2, 0.25), Vec3::new(3.25, 2.0, 0.0));
+        assert_eq!(interpolate_tick(position, last_pos, 2, 1.0), position);
+
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<KeyBindings>();
+        world.init_resource::<CameraSettings>();
+        world.insert_resource(CameraDevSettings { smooth_follow: true, ..default() });
+        world.insert_resource(PhysicsSettings { substeps: 2, ..default() });
+        let mut fixed_time = Time::<Fixed>::from_seconds(1.0);
+        fixed_time.accumulate(Duration::from_secs_f32(0.25));
+        world.insert_resource(fixed_time);
+        let body = world.spawn((GlobalTransform::from_translation(position), LastPos(last_pos))).id();
+        world.insert_resource(CameraFollow(Some(body)));
+
+        world.run_system_cached(follow_body).unwrap();
+        assert!((world.resource::<CameraSettings>().target - Vec3::new(3.25, 2.0, 0.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn orbit_pitch_clamps_at_the_range_boundaries() {
+        let camera_dev_settings = CameraDevSettings::default();
+        let range = camera_dev_settings.pitch_range.clone();
+
+        // Moving the mouse a long way should stop exactly at the limit, in either direction.
+        for (delta_y, limit) in [(1e6, range.start), (-1e6, range.end)] {
+            let (rotation, _) = orbit_step(Quat::IDENTITY, Vec2::new(0.0, delta_y), Vec3::ZERO, 20.0, &camera_dev_settings);
+            let (_, pitch, _) = rotation.to_euler(EulerRot::YXZ);
+            assert!((pitch - limit).abs() < 1e-4, "pitch {pitch} should be clamped to {limit}");
+        }
+    }
+
+    #[test]
+    fn orbit_yaw_wraps_past_half_a_turn() {
+        let camera_dev_settings = CameraDevSettings::default();
+        let start = Quat::from_euler(EulerRot::YXZ, 3.0, 0.2, 0.0);
+
+        // Turning 0.5 radians further than 3 passes PI and comes back around from -PI.
+        let delta_x = -0.5 / camera_dev_settings.yaw_speed;
+        let (rotation, _) = orbit_step(start, Vec2::new(delta_x, 0.0), Vec3::ZERO, 20.0, &camera_dev_settings);
+        let (yaw, pitch, _) = rotation.to_euler(EulerRot::YXZ);
+        assert!((yaw - wrap_angle(3.5)).abs() < 1e-4, "yaw {yaw} should be {}", wrap_angle(3.5));
+        assert!((pitch - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn orbit_translation_stays_at_orbit_distance_from_target() {
+        let camera_dev_settings = CameraDevSettings::default();
+        let target = Vec3::new(4.0, -2.0, 7.5);
+
+        for (yaw, pitch, delta) in [(0.0, 0.0, Vec2::ZERO), (1.0, 0.5, Vec2::new(300.0, -200.0)), (-2.5, -1.2, Vec2::new(-50.0, 900.0))] {
+            let start = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+            let (rotation, translation) = orbit_step(start, delta, target, 12.0, &camera_dev_settings);
+
+            assert!(((translation - target).length() - 12.0).abs() < 1e-4);
+            // The camera should also be looking straight at the target.
+            let forward = rotation * Vec3::NEG_Z;
+            assert!(forward.dot((target - translation).normalize()) > 1.0 - 1e-5);
+        }
+    }
+
+    #[test]
+    fn orbit_inertia_decays_to_a_stop() {
+        let camera_dev_settings = CameraDevSettings::default();
+        let dt = 1.0 / 60.0;
+
+        // Each step should be slower than the last, until it stops entirely and stays stopped.
+        let mut velocity = Vec2::new(2000.0, -500.0);
+        let mut steps = 0;
+        while velocity != Vec2::ZERO {
+            let next = coast(velocity, dt, &camera_dev_settings);
+            assert!(next.length() < velocity.length());
+            velocity = next;
+            steps += 1;
+            assert!(steps < 10_000, "inertia should stop within a reasonable time");
+        }
+        assert_eq!(coast(Vec2::ZERO, dt, &camera_dev_settings), Vec2::ZERO);
+    }
+
+    #[test]
+    fn zoom_clamps_at_the_ends_of_the_zoom_range() {
+        let camera_dev_settings = CameraDevSettings::default();
+        let range = camera_dev_settings.zoom_range.clone();
+
+        // Scrolling up zooms in, and scrolling down zooms out.
+        assert_eq!(zoom_step(range.start, 1.0, MouseScrollUnit::Line, &camera_dev_settings), range.start);
+        assert_eq!(zoom_step(range.end, -1.0, MouseScrollUnit::Line, &camera_dev_settings), range.end);
+
+        // A huge scroll from the middle should stop at the limits rather than overshoot them.
+        assert_eq!(zoom_step(50.0, 1e6, MouseScrollUnit::Line, &camera_dev_settings), range.start);
+        assert_eq!(zoom_step(50.0, -1e6, MouseScrollUnit::Line, &camera_dev_settings), range.end);
+    }
+
+    #[test]
+    fn zoom_pixel_scroll_is_a_tenth_as_sensitive_as_line_scroll() {
+        let camera_dev_settings = CameraDevSettings::default();
+
+        let line = zoom_step(50.0, 1.0, MouseScrollUnit::Line, &camera_dev_settings);
+        let pixels = zoom_step(50.0, PIXELS_PER_LINE, MouseScrollUnit::Pixel, &camera_dev_settings);
+        assert!((line - pixels).abs() < 1e-5);
+
+        let single_pixel = zoom_step(50.0, 1.0, MouseScrollUnit::Pixel, &camera_dev_settings);
+        assert!(((50.0 - single_pixel) * PIXELS_PER_LINE - (50.0 - line)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn zoom_without_scrolling_keeps_the_distance() {
+        let camera_dev_settings = CameraDevSettings::default();
+
+        for unit in [MouseScrollUnit::Line, MouseScrollUnit::Pixel] {
+            assert_eq!(zoom_step(20.0, 0.0, unit, &camera_dev_settings), 20.0);
+        }
+    }
+}
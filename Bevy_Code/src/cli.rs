@@ -0,0 +1,92 @@
+use std::{fs, path::PathBuf};
+use bevy::prelude::default;
+use clap::Parser;
+use serde::de::DeserializeOwned;
+use crate::{bodies::{PhysicsSettings, SimulationParams}, camera::CameraConfig};
+
+/// An n-body simulation of bodies orbiting a star.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Number of bodies to spawn, overriding the config file.
+    #[arg(long, value_name = "N")]
+    pub bodies: Option<usize>,
+
+    /// Seed for generating the bodies, so a run can be reproduced. Random if not given.
+    #[arg(long, value_name = "S")]
+    pub seed: Option<u64>,
+
+    /// RON file of simulation parameters and camera preferences to start with. Missing ones keep
+    /// their default.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Snapshot to start from instead of random bodies.
+    #[arg(long, value_name = "PATH")]
+    pub scene: Option<PathBuf>,
+
+    /// CSV or JSON file of body positions and masses to start from instead of random bodies.
+    /// Velocity, color, and radius can be given too, or left to their defaults.
+    #[arg(long, value_name = "PATH", conflicts_with = "scene")]
+    pub scenario: Option<PathBuf>,
+
+    /// Profile the physics for a number of ticks without rendering, instead of opening a window.
+    /// Always starts from random bodies, so it can't be given a scene or scenario.
+    #[arg(long, value_name = "TICKS", conflicts_with_all = ["scene", "scenario"])]
+    pub bench: Option<u32>,
+
+    /// Run the physics without a window or renderer for `--steps` ticks, and print how fast it went.
+    /// Like `--bench`, it always starts from random bodies.
+    #[arg(long, conflicts_with_all = ["bench", "scene", "scenario"])]
+    pub headless: bool,
+
+    /// Number of physics ticks to run in headless mode.
+    #[arg(long, value_name = "N", default_value_t = 1000, requires = "headless")]
+    pub steps: u32,
+
+    /// Integrate the bodies on a single thread, to compare against the default of spreading them across threads.
+    #[arg(long)]
+    pub serial_integration: bool,
+}
+
+impl Cli {
+    /// A function to build the starting simulation parameters from the config file and overrides.
+    pub fn simulation_params(&self) -> Result<SimulationParams, String> {
+        let mut params: SimulationParams = self.read_config()?;
+        if let Some(bodies) = self.bodies {
+            params.body_count = bodies;
+        }
+        Ok(params)
+    }
+
+    /// A function to read the camera preferences from the config file.
+    pub fn camera_config(&self) -> Result<CameraConfig, String> {
+        self.read_config()
+    }
+
+    /// A function to read part of the config file, or its defaults without one. The simulation
+    /// parameters and camera preferences sit side by side in the file, and each part skips over
+    /// the fields of the others.
+    fn read_config<T: DeserializeOwned + Default>(&self) -> Result<T, String> {
+        let Some(path) = &self.config else {
+            return Ok(T::default());
+        };
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read the config file {}: {err}", path.display()))?;
+        ron::from_str(&contents).map_err(|err| format!("cannot parse the config file {}: {err}", path.display()))
+    }
+
+    /// A function to find how many ticks to profile for without rendering, if the simulation isn't
+    /// opening a window at all.
+    pub fn headless_ticks(&self) -> Option<u32> {
+        self.bench.or(self.headless.then_some(self.steps))
+    }
+
+    /// A function to build the starting physics settings from the flags.
+    pub fn physics_settings(&self) -> PhysicsSettings {
+        PhysicsSettings {
+            parallel_integration: !self.serial_integration,
+            ..default()
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use bevy::{color::Mix, prelude::*};
+use crate::{
+    bodies::{BaseColor, Body, LastPos, Mass, PhysicsSettings},
+    grid::SpatialGrid,
+    materials::{paint_body, MaterialPalette},
+};
+
+/// Colors at the light and heavy ends of the mass gradient, which passes through white.
+const LIGHT_COLOR: Srgba = Srgba::rgb(0.2, 0.4, 1.0);
+const HEAVY_COLOR: Srgba = Srgba::rgb(1.0, 0.2, 0.1);
+/// Colors at the slow and fast ends of the speed gradient.
+const SLOW_COLOR: Srgba = Srgba::rgb(0.05, 0.05, 0.2);
+const FAST_COLOR: Srgba = Srgba::rgb(1.0, 0.95, 0.6);
+/// Colors at the sparse and dense ends of the density gradient, which passes through red like
+/// something heating up.
+const SPARSE_COLOR: Srgba = Srgba::rgb(0.1, 0.15, 0.5);
+const WARM_COLOR: Srgba = Srgba::rgb(0.9, 0.2, 0.05);
+const DENSE_COLOR: Srgba = Srgba::rgb(1.0, 0.95, 0.7);
+
+/// How the bodies are colored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum ColorMode {
+    /// Each body keeps the random color it spawned with.
+    #[default]
+    Random,
+    /// Bodies are colored along a gradient from the lightest to the heaviest.
+    ByMass,
+    /// Bodies are colored along a gradient from dark when slow to bright when fast.
+    BySpeed,
+    /// Bodies are colored by how many neighbors they have nearby, from cool when alone to hot in
+    /// dense clusters.
+    ByDensity,
+}
+
+/// Settings for coloring bodies by speed.
+#[derive(Debug, Resource)]
+pub struct SpeedColorSettings {
+    /// Speed at the bright end of the gradient, when not auto-scaling.
+    pub max_speed: f32,
+    /// Whether the bright end of the gradient follows the fastest body instead.
+    pub auto_scale: bool,
+}
+
+impl Default for SpeedColorSettings {
+    fn default() -> Self {
+        Self {
+            max_speed: 5.0,
+            auto_scale: true,
+        }
+    }
+}
+
+/// Settings for coloring bodies by how crowded they are.
+#[derive(Debug, Resource)]
+pub struct DensityColorSettings {
+    /// How close another body has to be to count as a neighbor.
+    pub neighbor_radius: f32,
+    /// Number of neighbors at the hot end of the gradient. Any more look the same.
+    pub max_neighbors: usize,
+}
+
+impl Default for DensityColorSettings {
+    fn default() -> Self {
+        Self {
+            neighbor_radius: 5.0,
+            max_neighbors: 20,
+        }
+    }
+}
+
+/// The range of body masses the mass gradient currently spans.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Resource)]
+pub struct MassRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+pub struct ColoringPlugin;
+
+impl Plugin for ColoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColorMode>()
+            .init_resource::<MassRange>()
+            .init_resource::<SpeedColorSettings>()
+            .init_resource::<DensityColorSettings>()
+            .add_systems(Update, (
+                recolor_bodies,
+                color_by_speed.run_if(|color_mode: Res<ColorMode>| *color_mode == ColorMode::BySpeed),
+                color_by_density.run_if(|color_mode: Res<ColorMode>| *color_mode == ColorMode::ByDensity),
+            ).chain());
+    }
+}
+
+/// A system to recolor bodies when the color mode changes, or when bodies spawn or change mass.
+#[allow(clippy::type_complexity)]
+fn recolor_bodies(
+    color_mode: Res<ColorMode>,
+    mut mass_range: ResMut<MassRange>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    mut query: Query<(Ref<Mass>, &BaseColor, &mut MeshMaterial3d<StandardMaterial>), With<Body>>,
+) {
+    let current_range = query.iter().fold(
+        MassRange {
+            min: f32::INFINITY,
+            max: 0.0,
+        },
+        |range, (mass, _, _)| MassRange {
+            min: range.min.min(mass.0),
+            max: range.max.max(mass.0),
+        },
+    );
+    // Without any bodies there are no masses to span, so the legend shows zeros rather than infinity.
+    let current_range = if current_range.min.is_finite() { current_range } else { MassRange::default() };
+
+    // Only write the range when it moves, so its change detection means something.
+    let range_changed = current_range != *mass_range;
+    if range_changed {
+        *mass_range = current_range;
+    }
+
+    // Speeds and densities change every frame, so they are colored separately.
+    if matches!(*color_mode, ColorMode::BySpeed | ColorMode::ByDensity) {
+        return;
+    }
+
+    // A new range shifts every body along the gradient, not just the ones that changed.
+    let recolor_all = color_mode.is_changed() || (range_changed && *color_mode == ColorMode::ByMass);
+
+    for (mass, base_color, mut material) in &mut query {
+        if !recolor_all && !mass.is_changed() {
+            continue;
+        }
+
+        let color = match *color_mode {
+            ColorMode::Random => base_color.0,
+            ColorMode::ByMass => mass_color(mass.0, &mass_range),
+            ColorMode::BySpeed | ColorMode::ByDensity => unreachable!("speed and density coloring returned early"),
+        };
+        paint_body(&mut material, color, &mut materials, &mut palette);
+    }
+}
+
+/// A system to color every body by its current speed.
+fn color_by_speed(
+    time: Res<Time<Fixed>>,
+    physics_settings: Res<PhysicsSettings>,
+    speed_color_settings: Res<SpeedColorSettings>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    mut query: Query<(&Transform, &LastPos, &mut MeshMaterial3d<StandardMaterial>), With<Body>>,
+) {
+    // Verlet integration keeps no velocity, so it is recovered from the last physics step.
+    let timestep = physics_settings.substep(time.timestep()).as_secs_f32();
+    let speed = |transform: &Transform, last_pos: &LastPos| {
+        transform.translation.distance(last_pos.0) / timestep
+    };
+
+    let max_speed = if speed_color_settings.auto_scale {
+        query
+            .iter()
+            .map(|(transform, last_pos, _)| speed(transform, last_pos))
+            .fold(0.0, f32::max)
+    } else {
+        speed_color_settings.max_speed
+    };
+
+    for (transform, last_pos, mut material) in &mut query {
+        let t = if max_speed > 0.0 {
+            (speed(transform, last_pos) / max_speed).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        paint_body(&mut material, SLOW_COLOR.mix(&FAST_COLOR, t).into(), &mut materials, &mut palette);
+    }
+}
+
+/// A system to color every body by how many other bodies are within the neighbor radius of it.
+fn color_by_density(
+    density_color_settings: Res<DensityColorSettings>,
+    grid: Res<SpatialGrid>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut palette: ResMut<MaterialPalette>,
+    mut query: Query<(Entity, &Transform, &mut MeshMaterial3d<StandardMaterial>), With<Body>>,
+) {
+    let max_neighbors = density_color_settings.max_neighbors.max(1) as f32;
+    for (entity, transform, mut material) in &mut query {
+        let count = neighbor_count(&grid, entity, transform.translation, density_color_settings.neighbor_radius);
+        let t = (count as f32 / max_neighbors).min(1.0);
+        let color = if t < 0.5 {
+            SPARSE_COLOR.mix(&WARM_COLOR, t * 2.0)
+        } else {
+            WARM_COLOR.mix(&DENSE_COLOR, t * 2.0 - 1.0)
+        };
+        paint_body(&mut material, color.into(), &mut materials, &mut palette);
+    }
+}
+
+/// A function to count the other bodies within the radius of a body, as of the last physics tick.
+pub fn neighbor_count(grid: &SpatialGrid, entity: Entity, position: Vec3, radius: f32) -> usize {
+    grid.within(position, radius).filter(|&other| other != entity).count()
+}
+
+/// A function to map a mass onto the gradient, logarithmically since mass grows with the cube of the radius.
+fn mass_color(mass: f32, mass_range: &MassRange) -> Color {
+    let span = (mass_range.max / mass_range.min).ln();
+    let t = if span.is_finite() && span > 0.0 {
+        ((mass / mass_range.min).ln() / span).clamp(0.0, 1.0)
+    } else {
+        // Bodies that all weigh the same sit in the middle of the gradient.
+        0.5
+    };
+
+    let color = if t < 0.5 {
+        LIGHT_COLOR.mix(&Srgba::WHITE, t * 2.0)
+    } else {
+        Srgba::WHITE.mix(&HEAVY_COLOR, t * 2.0 - 1.0)
+    };
+    color.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbors_are_counted_across_cells() {
+        // A tight cluster straddling cell boundaries, a pair just in range, and a loner.
+        let positions = [
+            Vec3::new(-0.1, -0.1, -0.1),
+            Vec3::new(0.1, 0.1, 0.1),
+            Vec3::new(0.1, -0.1, 0.1),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.999, 0.0),
+            Vec3::new(-20.0, 5.0, 3.0),
+        ];
+        let mut grid = SpatialGrid::new(1.0);
+        for (index, &position) in positions.iter().enumerate() {
+            grid.insert(Entity::from_raw(index as u32), position);
+        }
+        let counts = |radius| {
+            positions
+                .iter()
+                .enumerate()
+                .map(|(index, &position)| neighbor_count(&grid, Entity::from_raw(index as u32), position, radius))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(counts(1.0), [2, 2, 2, 1, 1, 0]);
+        assert_eq!(counts(0.0), [0; 6]);
+    }
+}
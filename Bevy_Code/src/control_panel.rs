@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContextPass, EguiContexts};
+use crate::{
+    background::{BackgroundMode, DEFAULT_GRADIENT},
+    bindings::KeyBindings,
+    bodies::{
+        CutoffMode, InteractionModel, MeshDetail, PeriodicBox, PhysicsSettings, RespawnBodies, SimulationParams, SphereStyle,
+        UniformGravityMode, MAX_MESH_DETAIL,
+    },
+    lighting::{LightingSettings, AMBIENT_BRIGHTNESS_RANGE},
+};
+
+/// Most bodies the body count slider goes up to. More can still be typed in.
+const MAX_SLIDER_BODIES: usize = 5000;
+
+/// Whether the physics tuning panel is shown.
+#[derive(Debug, Default, Resource)]
+pub struct ControlPanel {
+    pub visible: bool,
+}
+
+pub struct ControlPanelPlugin;
+
+impl Plugin for ControlPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ControlPanel>()
+            .add_systems(Update, toggle_control_panel)
+            .add_systems(EguiContextPass, draw_control_panel.run_if(|panel: Res<ControlPanel>| panel.visible));
+    }
+}
+
+/// A system to show or hide the control panel.
+fn toggle_control_panel(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut panel: ResMut<ControlPanel>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_control_panel) {
+        panel.visible = !panel.visible;
+    }
+}
+
+/// A system to draw a side panel of sliders for tuning the physics, body count, lighting, and
+/// background while it runs.
+fn draw_control_panel(
+    mut contexts: EguiContexts,
+    mut physics_settings: ResMut<PhysicsSettings>,
+    mut params: ResMut<SimulationParams>,
+    mut respawn_events: EventWriter<RespawnBodies>,
+    mut background_mode: ResMut<BackgroundMode>,
+    mut lighting_settings: ResMut<LightingSettings>,
+    mut mesh_detail: ResMut<MeshDetail>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    // Edit a copy, so the settings are only marked changed when a slider actually moves.
+    let mut settings = physics_settings.clone();
+    let mut body_count = params.body_count;
+    let mut background = *background_mode;
+    let mut ambient_brightness = lighting_settings.ambient_brightness;
+    let mut detail = mesh_detail.clone();
+    egui::SidePanel::left("control_panel").show(ctx, |ui| {
+        ui.heading("Physics");
+        ui.add(egui::Slider::new(&mut settings.gravity, 0.0..=1.0).text("Gravity"));
+        ui.checkbox(&mut settings.central_gravity_enabled, "Central gravity");
+        let mut uniform = settings.uniform_gravity.is_some();
+        ui.checkbox(&mut uniform, "Uniform gravity");
+        if uniform != settings.uniform_gravity.is_some() {
+            settings.uniform_gravity = uniform.then_some(Vec3::new(0.0, -9.8, 0.0));
+        }
+        if let Some(field) = &mut settings.uniform_gravity {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut field.x).speed(0.1).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut field.y).speed(0.1).prefix("y: "));
+                ui.add(egui::DragValue::new(&mut field.z).speed(0.1).prefix("z: "));
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut settings.uniform_gravity_mode, UniformGravityMode::Stack, "With other gravity");
+                ui.radio_value(&mut settings.uniform_gravity_mode, UniformGravityMode::Replace, "Instead");
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label("Interaction");
+            let mut lennard_jones = matches!(settings.interaction_model, InteractionModel::LennardJones { .. });
+            ui.radio_value(&mut lennard_jones, false, "Repulsion");
+            ui.radio_value(&mut lennard_jones, true, "Lennard-Jones");
+            if lennard_jones != matches!(settings.interaction_model, InteractionModel::LennardJones { .. }) {
+                settings.interaction_model = if lennard_jones {
+                    InteractionModel::LennardJones { epsilon: 1.0, sigma: 2.0 }
+                } else {
+                    InteractionModel::Repulsion
+                };
+            }
+        });
+        match &mut settings.interaction_model {
+            InteractionModel::Repulsion => {
+                ui.add(egui::Slider::new(&mut settings.repulsion, 0.0..=100.0).text("Repulsion"));
+                ui.add(egui::Slider::new(&mut settings.repulsion_exponent, 1.0..=4.0).text("Repulsion exponent"));
+            }
+            InteractionModel::LennardJones { epsilon, sigma } => {
+                ui.add(egui::Slider::new(epsilon, 0.0..=10.0).text("Well depth"));
+                ui.add(egui::Slider::new(sigma, 0.5..=10.0).text("Sigma"));
+            }
+        }
+        ui.add(egui::Slider::new(&mut settings.damping, 0.0..=0.05).text("Damping"));
+        ui.horizontal(|ui| {
+            ui.label("Cutoff");
+            ui.radio_value(&mut settings.cutoff_mode, CutoffMode::Absolute, "Absolute");
+            ui.radio_value(&mut settings.cutoff_mode, CutoffMode::Relative, "Relative to size");
+        });
+        match settings.cutoff_mode {
+            CutoffMode::Absolute => ui.add(egui::Slider::new(&mut settings.force_cutoff, 1.0..=50.0).text("Force cutoff")),
+            CutoffMode::Relative => ui.add(egui::Slider::new(&mut settings.relative_cutoff, 1.0..=20.0).text("Cutoff radii")),
+        };
+        ui.add(egui::Slider::new(&mut settings.softening, 0.0..=5.0).text("Softening"));
+        let mut periodic = settings.periodic_box.is_some();
+        ui.checkbox(&mut periodic, "Wrap around");
+        if periodic != settings.periodic_box.is_some() {
+            settings.periodic_box = periodic.then_some(PeriodicBox { half_extent: 100.0 });
+        }
+        if let Some(periodic_box) = &mut settings.periodic_box {
+            ui.add(egui::Slider::new(&mut periodic_box.half_extent, 10.0..=500.0).text("Box half width"));
+        }
+        ui.add(egui::Slider::new(&mut settings.time_scale, 0.0..=4.0).text("Time scale"));
+        ui.add(egui::Slider::new(&mut settings.fragmentation_threshold, 0.0..=100.0).text("Fragmentation speed"));
+        ui.checkbox(&mut settings.gpu_forces, "Forces on the GPU");
+
+        ui.separator();
+        ui.heading("Bodies");
+        // Bodies are added or removed to match as the count changes, and respawning starts over with it.
+        ui.add(egui::Slider::new(&mut body_count, 0..=MAX_SLIDER_BODIES).text("Count").clamping(egui::SliderClamping::Never));
+        if ui.button("Respawn").clicked() {
+            respawn_events.write(RespawnBodies);
+        }
+        ui.horizontal(|ui| {
+            ui.label("Mesh");
+            ui.radio_value(&mut detail.style, SphereStyle::Ico, "Icosphere");
+            ui.radio_value(&mut detail.style, SphereStyle::Uv, "UV sphere");
+        });
+        // Farther bodies still drop to coarser meshes than this as they shrink on screen.
+        ui.add(egui::Slider::new(&mut detail.detail, 0..=MAX_MESH_DETAIL).text("Mesh detail"));
+
+        ui.separator();
+        ui.heading("Lighting");
+        ui.add(egui::Slider::new(&mut ambient_brightness, AMBIENT_BRIGHTNESS_RANGE).text("Ambient light"));
+        ui.horizontal(|ui| {
+            ui.label("Background");
+            let mut gradient = matches!(background, BackgroundMode::Gradient(..));
+            ui.radio_value(&mut gradient, false, "Solid");
+            ui.radio_value(&mut gradient, true, "Gradient");
+            // Switching keeps the bottom color, which is the one filling most of a dark sky.
+            background = match (background, gradient) {
+                (BackgroundMode::Solid(color), true) => BackgroundMode::Gradient(DEFAULT_GRADIENT.0, color),
+                (BackgroundMode::Gradient(_, bottom), false) => BackgroundMode::Solid(bottom),
+                (mode, _) => mode,
+            };
+        });
+        ui.horizontal(|ui| match &mut background {
+            BackgroundMode::Solid(color) => color_button(ui, color),
+            BackgroundMode::Gradient(top, bottom) => {
+                ui.label("Top");
+                color_button(ui, top);
+                ui.label("Bottom");
+                color_button(ui, bottom);
+            }
+        });
+    });
+
+    if *physics_settings != settings {
+        *physics_settings = settings;
+    }
+    if params.body_count != body_count {
+        params.body_count = body_count;
+    }
+    if lighting_settings.ambient_brightness != ambient_brightness {
+        lighting_settings.ambient_brightness = ambient_brightness;
+    }
+    if *mesh_detail != detail {
+        *mesh_detail = detail;
+    }
+    if *background_mode != background {
+        *background_mode = background;
+    }
+}
+
+/// A function to show a color picker for a color, only replacing it when a new one is picked so
+/// converting to and from sRGB doesn't nudge it every frame.
+fn color_button(ui: &mut egui::Ui, color: &mut Color) {
+    let mut rgb = color.to_srgba().to_f32_array_no_alpha();
+    if ui.color_edit_button_rgb(&mut rgb).changed() {
+        *color = Color::srgb_from_array(rgb);
+    }
+}
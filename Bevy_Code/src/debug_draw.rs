@@ -0,0 +1,339 @@
+use std::f32::consts::FRAC_PI_2;
+use bevy::prelude::*;
+use crate::{
+    bindings::KeyBindings,
+    bodies::{
+        central_gravity_acceleration, mutual_gravity_accelerations, repulsion_accelerations, Acceleration, Body, LastPos, Mass, PhysicsSettings, Radius,
+        SimulationParams, Spring, Star,
+    },
+};
+
+/// Half the length of each arm of the center of mass crosshair.
+const CROSSHAIR_SIZE: f32 = 1.5;
+/// Radius of the ring marking the center of gravity.
+const GRAVITY_CENTER_RING_RADIUS: f32 = 3.0;
+/// Segments in each circle of a bounding sphere, kept low so there can be many of them.
+const BOUNDING_SPHERE_RESOLUTION: u32 = 16;
+/// Size of the imaginary body the force field is sampled with, which decides how hard it is pushed.
+const PROBE_RADIUS: f32 = 1.0;
+
+/// Toggles for the debug gizmos drawn over the simulation.
+#[derive(Debug, Resource)]
+pub struct DebugDrawSettings {
+    pub center_of_mass: bool,
+    pub centroid: bool,
+    pub force_vectors: bool,
+    /// Length of a force arrow per unit of acceleration.
+    pub force_scale: f32,
+    pub velocity_vectors: bool,
+    /// Length of a velocity arrow per unit of speed.
+    pub velocity_scale: f32,
+    /// A grid in the horizontal plane, axis lines at the origin, and a ring at the center of gravity.
+    pub reference_grid: bool,
+    pub grid_spacing: f32,
+    /// Width of the whole grid, centered on the origin.
+    pub grid_extent: f32,
+    /// Arrows showing the acceleration a body would feel at points on a grid around the bodies.
+    /// Off by default, since every point samples the force from every body.
+    pub force_field: bool,
+    /// Number of points along each side of the force field grid.
+    pub force_field_resolution: u32,
+    /// Width of the force field grid, centered on the centroid of the bodies.
+    pub force_field_extent: f32,
+    /// A wireframe sphere of each body's radius, which should line up with its mesh.
+    pub bounding_spheres: bool,
+    pub bounding_sphere_color: Color,
+}
+
+impl Default for DebugDrawSettings {
+    fn default() -> Self {
+        Self {
+            center_of_mass: false,
+            centroid: false,
+            force_vectors: false,
+            force_scale: 0.2,
+            velocity_vectors: false,
+            velocity_scale: 0.5,
+            reference_grid: false,
+            grid_spacing: 5.0,
+            grid_extent: 100.0,
+            force_field: false,
+            force_field_resolution: 8,
+            force_field_extent: 40.0,
+            bounding_spheres: false,
+            bounding_sphere_color: Color::srgba(0.2, 1.0, 0.4, 0.6),
+        }
+    }
+}
+
+pub struct DebugDrawPlugin;
+
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugDrawSettings>()
+            .add_systems(Update, (
+                toggle_debug_draw,
+                draw_centers.run_if(|settings: Res<DebugDrawSettings>| settings.center_of_mass || settings.centroid),
+                draw_force_vectors.run_if(|settings: Res<DebugDrawSettings>| settings.force_vectors),
+                draw_velocity_vectors.run_if(|settings: Res<DebugDrawSettings>| settings.velocity_vectors),
+                draw_reference_grid.run_if(|settings: Res<DebugDrawSettings>| settings.reference_grid),
+                draw_force_field.run_if(|settings: Res<DebugDrawSettings>| settings.force_field),
+                draw_bounding_spheres.run_if(|settings: Res<DebugDrawSettings>| settings.bounding_spheres),
+                // Springs are invisible otherwise, so they are always drawn.
+                draw_springs.run_if(any_with_component::<Spring>),
+            ));
+    }
+}
+
+/// A system to toggle each of the debug gizmos.
+fn toggle_debug_draw(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut settings: ResMut<DebugDrawSettings>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_center_of_mass) {
+        settings.center_of_mass = !settings.center_of_mass;
+    }
+    if key_input.just_pressed(key_bindings.toggle_centroid) {
+        settings.centroid = !settings.centroid;
+    }
+    if key_input.just_pressed(key_bindings.toggle_force_vectors) {
+        settings.force_vectors = !settings.force_vectors;
+    }
+    if key_input.just_pressed(key_bindings.toggle_velocity_vectors) {
+        settings.velocity_vectors = !settings.velocity_vectors;
+    }
+    if key_input.just_pressed(key_bindings.toggle_reference_grid) {
+        settings.reference_grid = !settings.reference_grid;
+    }
+    if key_input.just_pressed(key_bindings.toggle_force_field) {
+        settings.force_field = !settings.force_field;
+    }
+    if key_input.just_pressed(key_bindings.toggle_bounding_spheres) {
+        settings.bounding_spheres = !settings.bounding_spheres;
+    }
+}
+
+/// A system to mark the mass-weighted and geometric centers of the bodies.
+fn draw_centers(
+    mut gizmos: Gizmos,
+    settings: Res<DebugDrawSettings>,
+    query: Query<(&Mass, &GlobalTransform), With<Body>>,
+) {
+    // Accumulate both averages in a single pass over the bodies.
+    let mut total_mass = 0.0;
+    let mut weighted_sum = Vec3::ZERO;
+    let mut position_sum = Vec3::ZERO;
+    let mut count = 0;
+    for (mass, transform) in &query {
+        total_mass += mass.0;
+        weighted_sum += mass.0 * transform.translation();
+        position_sum += transform.translation();
+        count += 1;
+    }
+
+    if count == 0 {
+        return;
+    }
+
+    if settings.center_of_mass && total_mass > 0.0 {
+        let center = weighted_sum / total_mass;
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            gizmos.line(center - axis * CROSSHAIR_SIZE, center + axis * CROSSHAIR_SIZE, Color::WHITE);
+        }
+    }
+
+    if settings.centroid {
+        let centroid = position_sum / count as f32;
+        gizmos.sphere(Isometry3d::from_translation(centroid), 0.5, Color::srgb(0.3, 0.8, 1.0));
+    }
+}
+
+/// A system to draw an arrow along the acceleration of each body, colored from blue to red by magnitude.
+fn draw_force_vectors(
+    mut gizmos: Gizmos,
+    settings: Res<DebugDrawSettings>,
+    query: Query<(&Acceleration, &GlobalTransform), With<Body>>,
+) {
+    // Accelerations are only cleared at the start of the next physics step, so by the time
+    // Update runs they hold the full total from the step that just finished.
+    let max_magnitude = query
+        .iter()
+        .map(|(acceleration, _)| acceleration.0.length())
+        .fold(0.0, f32::max);
+    if max_magnitude <= 0.0 {
+        return;
+    }
+
+    for (acceleration, transform) in &query {
+        let start = transform.translation();
+        let t = acceleration.0.length() / max_magnitude;
+        let color = Color::hsl(240.0 * (1.0 - t), 1.0, 0.5);
+        gizmos.arrow(start, start + acceleration.0 * settings.force_scale, color);
+    }
+}
+
+/// A system to draw an arrow along the velocity of each body, colored from blue to red by speed.
+fn draw_velocity_vectors(
+    mut gizmos: Gizmos,
+    settings: Res<DebugDrawSettings>,
+    time: Res<Time<Fixed>>,
+    physics_settings: Res<PhysicsSettings>,
+    query: Query<(&Transform, &LastPos), With<Body>>,
+) {
+    // Verlet integration keeps no velocity, so it is recovered from the last physics step.
+    let timestep = physics_settings.substep(time.timestep()).as_secs_f32();
+    let velocity = |transform: &Transform, last_pos: &LastPos| (transform.translation - last_pos.0) / timestep;
+
+    let max_speed = query
+        .iter()
+        .map(|(transform, last_pos)| velocity(transform, last_pos).length())
+        .fold(0.0, f32::max);
+    if max_speed <= 0.0 {
+        return;
+    }
+
+    for (transform, last_pos) in &query {
+        let start = transform.translation;
+        let body_velocity = velocity(transform, last_pos);
+        let t = body_velocity.length() / max_speed;
+        let color = Color::hsl(240.0 * (1.0 - t), 1.0, 0.5);
+        gizmos.arrow(start, start + body_velocity * settings.velocity_scale, color);
+    }
+}
+
+/// A system to draw the combined gravity and push of the bodies at points on a grid around the
+/// bodies, colored from blue to red by magnitude. Points inside a body are skipped.
+fn draw_force_field(
+    mut gizmos: Gizmos,
+    settings: Res<DebugDrawSettings>,
+    physics_settings: Res<PhysicsSettings>,
+    star: Option<Single<(&Mass, &Transform), With<Star>>>,
+    bodies: Query<(&Mass, &Radius, &Transform), With<Body>>,
+) {
+    let count = bodies.iter().count();
+    if count == 0 || settings.force_field_resolution == 0 {
+        return;
+    }
+    let centroid = bodies.iter().map(|(_, _, transform)| transform.translation).sum::<Vec3>() / count as f32;
+
+    // The same force math and switches the physics uses, felt by a probe body at each point.
+    let acceleration_at = |point: Vec3| {
+        let mut acceleration = star.as_ref().map_or(Vec3::ZERO, |star| {
+            let (star_mass, star_transform) = **star;
+            central_gravity_acceleration(star_transform.translation, star_mass.0, point, &physics_settings)
+        });
+        if let Some(field) = physics_settings.uniform_gravity {
+            acceleration += field;
+        }
+        let mutual_gravity = physics_settings.mutual_gravity_active();
+        for (mass, radius, transform) in &bodies {
+            let offset = physics_settings.separation(point, transform.translation);
+            if offset.length() < radius.0 {
+                return None;
+            }
+            // The probe's own mass doesn't change how hard it is pushed or pulled, only how hard
+            // it pushes and pulls back.
+            if let Some([push, _]) = repulsion_accelerations(offset, 1.0, PROBE_RADIUS, mass.0, radius.0, &physics_settings) {
+                acceleration += push;
+            }
+            if mutual_gravity {
+                acceleration += mutual_gravity_accelerations(offset, 1.0, mass.0, &physics_settings)[0];
+            }
+        }
+        Some(acceleration)
+    };
+
+    let resolution = settings.force_field_resolution;
+    let spacing = settings.force_field_extent / resolution as f32;
+    let start = centroid - Vec3::splat(settings.force_field_extent / 2.0 - spacing / 2.0);
+    let samples: Vec<(Vec3, Vec3)> = (0..resolution.pow(3))
+        .filter_map(|index| {
+            let cell = UVec3::new(index % resolution, index / resolution % resolution, index / resolution.pow(2));
+            let point = start + cell.as_vec3() * spacing;
+            acceleration_at(point).map(|acceleration| (point, acceleration))
+        })
+        .collect();
+
+    let max_magnitude = samples.iter().map(|(_, acceleration)| acceleration.length()).fold(0.0, f32::max);
+    if max_magnitude <= 0.0 {
+        return;
+    }
+    for (point, acceleration) in samples {
+        let t = acceleration.length() / max_magnitude;
+        let color = Color::hsl(240.0 * (1.0 - t), 1.0, 0.5);
+        gizmos.arrow(point, point + acceleration * settings.force_scale, color);
+    }
+}
+
+/// A system to draw a sphere of each body's radius around it, including the star's, to check
+/// the radius the physics uses against the rendered mesh.
+fn draw_bounding_spheres(
+    mut gizmos: Gizmos,
+    settings: Res<DebugDrawSettings>,
+    query: Query<(&Radius, &GlobalTransform)>,
+) {
+    for (radius, transform) in &query {
+        gizmos
+            .sphere(Isometry3d::from_translation(transform.translation()), radius.0, settings.bounding_sphere_color)
+            .resolution(BOUNDING_SPHERE_RESOLUTION);
+    }
+}
+
+/// A system to draw a line along each spring, colored by how far it is stretched or compressed.
+fn draw_springs(
+    mut gizmos: Gizmos,
+    springs: Query<&Spring>,
+    bodies: Query<&Transform, With<Body>>,
+) {
+    for spring in &springs {
+        // Springs that lost a body are removed by the physics, so they are skipped until then.
+        let Ok([transform_a, transform_b]) = bodies.get_many([spring.a, spring.b]) else {
+            continue;
+        };
+        let length = transform_a.translation.distance(transform_b.translation);
+        let strain = ((length - spring.rest_length) / spring.rest_length.max(f32::EPSILON)).clamp(-1.0, 1.0);
+        // Green at rest, turning red when stretched and blue when compressed.
+        let color = Color::hsl(120.0 - 120.0 * strain, 1.0, 0.5);
+        gizmos.line(transform_a.translation, transform_b.translation, color);
+    }
+}
+
+/// A system to draw a faint horizontal grid and axis lines through the origin, and a ring at the
+/// center of gravity, to judge distances and directions by.
+fn draw_reference_grid(
+    mut gizmos: Gizmos,
+    settings: Res<DebugDrawSettings>,
+    params: Res<SimulationParams>,
+) {
+    // Grids and circles are drawn in the XY plane, so tip them over into the horizontal one.
+    let horizontal = Quat::from_rotation_x(FRAC_PI_2);
+
+    if settings.grid_spacing > 0.0 {
+        let cells = (settings.grid_extent / settings.grid_spacing).round() as u32;
+        gizmos.grid(
+            Isometry3d::from_rotation(horizontal),
+            UVec2::splat(cells),
+            Vec2::splat(settings.grid_spacing),
+            Color::srgba(1.0, 1.0, 1.0, 0.15),
+        );
+    }
+
+    // Axes run through the origin both ways, faded on the negative side to tell the directions apart.
+    // Gizmos are depth tested like the meshes, so bodies in front of the lines still hide them.
+    let axis_length = settings.grid_extent / 2.0;
+    for (axis, color) in [
+        (Vec3::X, Srgba::rgb(1.0, 0.2, 0.2)),
+        (Vec3::Y, Srgba::rgb(0.2, 1.0, 0.2)),
+        (Vec3::Z, Srgba::rgb(0.2, 0.4, 1.0)),
+    ] {
+        gizmos.line(Vec3::ZERO, axis * axis_length, color);
+        gizmos.line(Vec3::ZERO, -axis * axis_length, color.with_alpha(0.35));
+    }
+
+    gizmos.circle(
+        Isometry3d::new(params.gravity_center, horizontal),
+        GRAVITY_CENTER_RING_RADIUS,
+        Color::srgb(1.0, 0.85, 0.3),
+    );
+}
@@ -0,0 +1,483 @@
+use std::collections::VecDeque;
+use bevy::{
+    asset::{load_internal_asset, weak_handle},
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssets,
+        render_graph::{self, RenderGraph, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer, storage_buffer_read_only},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        storage::{GpuShaderStorageBuffer, ShaderStorageBuffer},
+        Render, RenderApp, RenderSet,
+    },
+};
+use crate::bodies::{
+    clear_accelerations, integrate, Acceleration, CutoffMode, GpuForcesReady, GravityScale, Mass, PhysicsSettings, PhysicsStep,
+    Radius, Star,
+};
+
+const FORCES_SHADER: Handle<Shader> = weak_handle!("5f0c3b52-8e6d-4a8e-9c1f-2b7d6e4a9f13");
+// Invocations per workgroup, matching the shader.
+const WORKGROUP_SIZE: u32 = 64;
+// Uploads kept waiting for their results, beyond which the oldest are given up on.
+const MAX_PENDING_UPLOADS: usize = 8;
+
+/// The settings the shader needs, uploaded alongside the bodies.
+#[derive(ShaderType, Clone, Copy, Default)]
+struct GpuForceParams {
+    star_position: Vec3,
+    star_mass: f32,
+    gravity: f32,
+    repulsion: f32,
+    repulsion_exponent: f32,
+    force_cutoff: f32,
+    // A multiple of the combined radii to cut off at instead of the absolute distance, or 0 for none.
+    relative_cutoff: f32,
+    // Half the width of the box space wraps around in, or 0 if it doesn't.
+    periodic_half_extent: f32,
+    min_distance: f32,
+    softening: f32,
+    body_count: u32,
+    frame: u32,
+}
+
+/// A body as the shader sees it.
+#[derive(ShaderType, Clone, Copy, Default)]
+struct GpuBody {
+    position: Vec3,
+    mass: f32,
+    radius: f32,
+    gravity_scale: f32,
+}
+
+/// A body's acceleration as calculated by the shader, tagged with the upload it came from.
+#[derive(ShaderType, Clone, Copy, Default)]
+struct GpuAcceleration {
+    acceleration: Vec3,
+    frame: u32,
+}
+
+/// The storage buffers shared with the shader, and how many bodies to run it over.
+#[derive(Resource, ExtractResource, Clone)]
+struct GpuForceBuffers {
+    params: Handle<ShaderStorageBuffer>,
+    bodies: Handle<ShaderStorageBuffer>,
+    accelerations: Handle<ShaderStorageBuffer>,
+    body_count: u32,
+    enabled: bool,
+}
+
+/// The bodies in each upload still waiting on their results, and the latest results read back
+/// with the upload they came from. There is one upload each physics tick.
+#[derive(Default, Resource)]
+struct GpuAccelerations {
+    frame: u32,
+    pending: VecDeque<(u32, Vec<Entity>)>,
+    latest: Vec<(Entity, Vec3)>,
+    latest_frame: u32,
+}
+
+impl GpuAccelerations {
+    /// Whether the latest results were calculated from where the bodies were at most a tick ago.
+    /// Older ones have the bodies pushing each other from where they used to be, which adds energy
+    /// to every contact, so the CPU takes over until fresher ones arrive.
+    fn fresh(&self) -> bool {
+        !self.latest.is_empty() && self.latest_frame + 1 >= self.frame
+    }
+}
+
+pub struct GpuForcesPlugin;
+
+impl Plugin for GpuForcesPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, FORCES_SHADER, "gpu_forces.wgsl", Shader::from_wgsl);
+
+        app.init_resource::<GpuAccelerations>()
+            .add_plugins(ExtractResourcePlugin::<GpuForceBuffers>::default())
+            .add_systems(Startup, setup_gpu_forces)
+            // Once a tick, so every upload is of where the bodies were at the start of a tick.
+            .add_systems(FixedPreUpdate, (upload_bodies, check_gpu_accelerations).chain())
+            // The GPU results stand in for the CPU's gravity and repulsion, which are skipped.
+            .add_systems(PhysicsStep, apply_gpu_accelerations
+                .after(clear_accelerations)
+                .before(integrate)
+                .run_if(|gpu_forces_ready: Res<GpuForcesReady>| gpu_forces_ready.0));
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Without a renderer there is no GPU to run on, and the CPU keeps doing the work.
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<GpuForcesPipeline>()
+            .add_systems(Render, prepare_bind_group.in_set(RenderSet::PrepareBindGroups));
+
+        let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+        render_graph.add_node(GpuForcesLabel, GpuForcesNode);
+        render_graph.add_node_edge(GpuForcesLabel, bevy::render::graph::CameraDriverLabel);
+    }
+}
+
+/// A system to create the storage buffers, and read the accelerations back every frame.
+fn setup_gpu_forces(mut commands: Commands, mut storage_buffers: ResMut<Assets<ShaderStorageBuffer>>) {
+    // Buffers can't be empty, so they start with room for one body.
+    let mut accelerations = ShaderStorageBuffer::from(vec![GpuAcceleration::default()]);
+    accelerations.buffer_description.usage |= BufferUsages::COPY_SRC;
+    let accelerations = storage_buffers.add(accelerations);
+
+    commands.spawn(Readback::buffer(accelerations.clone())).observe(store_gpu_accelerations);
+    commands.insert_resource(GpuForceBuffers {
+        params: storage_buffers.add(ShaderStorageBuffer::from(GpuForceParams::default())),
+        bodies: storage_buffers.add(ShaderStorageBuffer::from(vec![GpuBody::default()])),
+        accelerations,
+        body_count: 0,
+        enabled: false,
+    });
+}
+
+/// A system to upload the bodies and settings for the shader to run over, as of the start of this
+/// physics tick.
+#[allow(clippy::type_complexity)]
+fn upload_bodies(
+    physics_settings: Res<PhysicsSettings>,
+    mut gpu_force_buffers: ResMut<GpuForceBuffers>,
+    mut gpu_accelerations: ResMut<GpuAccelerations>,
+    mut storage_buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    star: Option<Single<(&Mass, &Transform), With<Star>>>,
+    bodies: Query<(Entity, &Transform, &Mass, &Radius, Option<&GravityScale>), With<Acceleration>>,
+) {
+    if gpu_force_buffers.enabled != physics_settings.gpu_forces {
+        gpu_force_buffers.enabled = physics_settings.gpu_forces;
+    }
+    if !physics_settings.gpu_forces {
+        // Results left over from before would be stale by the time it is turned on again.
+        if !gpu_accelerations.latest.is_empty() {
+            gpu_accelerations.latest.clear();
+            gpu_accelerations.pending.clear();
+        }
+        return;
+    }
+
+    let (entities, mut gpu_bodies): (Vec<_>, Vec<_>) = bodies
+        .iter()
+        .map(|(entity, transform, mass, radius, gravity_scale)| {
+            (entity, GpuBody {
+                position: transform.translation,
+                mass: mass.0,
+                radius: radius.0,
+                gravity_scale: gravity_scale.map_or(1.0, |gravity_scale| gravity_scale.0),
+            })
+        })
+        .unzip();
+    let body_count = gpu_bodies.len() as u32;
+
+    // Frames start at 1, so results from an accelerations buffer that has never been written are ignored.
+    gpu_accelerations.frame += 1;
+    let frame = gpu_accelerations.frame;
+    // A massless star pulls on nothing, which is how the shader is told central gravity is off.
+    let (star_mass, star_position) = star
+        .filter(|_| physics_settings.central_gravity_active())
+        .map_or((0.0, Vec3::ZERO), |star| {
+            let (mass, transform) = *star;
+            (mass.0, transform.translation)
+        });
+    let params = GpuForceParams {
+        star_position,
+        star_mass,
+        gravity: physics_settings.gravity,
+        // Forces the shader doesn't know are summed on the CPU instead, so it is told of no push at all.
+        repulsion: if physics_settings.interactions_on_gpu() { physics_settings.repulsion } else { 0.0 },
+        repulsion_exponent: physics_settings.repulsion_exponent,
+        force_cutoff: physics_settings.force_cutoff,
+        relative_cutoff: match physics_settings.cutoff_mode {
+            CutoffMode::Absolute => 0.0,
+            CutoffMode::Relative => physics_settings.relative_cutoff,
+        },
+        periodic_half_extent: physics_settings.periodic_box.map_or(0.0, |periodic_box| periodic_box.half_extent),
+        min_distance: physics_settings.min_distance,
+        softening: physics_settings.softening,
+        body_count,
+        frame,
+    };
+
+    if let Some(buffer) = storage_buffers.get_mut(&gpu_force_buffers.params) {
+        buffer.set_data(params);
+    }
+    // Buffers can't be empty, so with no bodies a placeholder is uploaded that the shader never reads.
+    if gpu_bodies.is_empty() {
+        gpu_bodies.push(GpuBody::default());
+    }
+    if let Some(buffer) = storage_buffers.get_mut(&gpu_force_buffers.bodies) {
+        buffer.set_data(gpu_bodies);
+    }
+    // The accelerations buffer is only replaced when it needs to change size, since the shader
+    // writes into it in place.
+    if gpu_force_buffers.body_count != body_count {
+        gpu_force_buffers.body_count = body_count;
+        let accelerations = vec![GpuAcceleration::default(); body_count.max(1) as usize];
+        if let Some(buffer) = storage_buffers.get_mut(&gpu_force_buffers.accelerations) {
+            buffer.set_data(accelerations);
+        }
+    }
+
+    gpu_accelerations.pending.push_back((frame, entities));
+    while gpu_accelerations.pending.len() > MAX_PENDING_UPLOADS {
+        gpu_accelerations.pending.pop_front();
+    }
+}
+
+/// An observer to match accelerations read back from the GPU with the bodies they were calculated for.
+fn store_gpu_accelerations(trigger: Trigger<ReadbackComplete>, mut gpu_accelerations: ResMut<GpuAccelerations>) {
+    let results: Vec<GpuAcceleration> = trigger.event().to_shader_type();
+    let Some(frame) = results.first().map(|result| result.frame) else {
+        return;
+    };
+
+    // Uploads older than these results will never be read back now.
+    while gpu_accelerations.pending.front().is_some_and(|(pending_frame, _)| *pending_frame < frame) {
+        gpu_accelerations.pending.pop_front();
+    }
+    let Some((pending_frame, entities)) = gpu_accelerations.pending.front() else {
+        return;
+    };
+    if *pending_frame != frame || entities.len() > results.len() {
+        return;
+    }
+    let latest = entities
+        .iter()
+        .zip(&results)
+        .map(|(&entity, result)| (entity, result.acceleration))
+        .collect();
+    gpu_accelerations.latest = latest;
+    gpu_accelerations.latest_frame = frame;
+}
+
+/// A system to decide whether this tick uses the accelerations from the GPU, or sums the forces on
+/// the CPU because the latest ones are too old.
+fn check_gpu_accelerations(
+    physics_settings: Res<PhysicsSettings>,
+    gpu_accelerations: Res<GpuAccelerations>,
+    mut gpu_forces_ready: ResMut<GpuForcesReady>,
+) {
+    let ready = physics_settings.gpu_forces && gpu_accelerations.fresh();
+    if gpu_forces_ready.0 != ready {
+        gpu_forces_ready.0 = ready;
+    }
+}
+
+/// A system to add the latest accelerations from the GPU to the bodies. Bodies spawned since the
+/// last upload aren't pushed or pulled until their results arrive.
+fn apply_gpu_accelerations(gpu_accelerations: Res<GpuAccelerations>, mut query: Query<&mut Acceleration>) {
+    for &(entity, acceleration) in &gpu_accelerations.latest {
+        if let Ok(mut body_acceleration) = query.get_mut(entity) {
+            body_acceleration.0 += acceleration;
+        }
+    }
+}
+
+/// The compute pipeline summing the forces, and the layout of the buffers bound to it.
+#[derive(Resource)]
+struct GpuForcesPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for GpuForcesPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "gpu_forces_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    storage_buffer_read_only::<GpuForceParams>(false),
+                    storage_buffer_read_only::<Vec<GpuBody>>(false),
+                    storage_buffer::<Vec<GpuAcceleration>>(false),
+                ),
+            ),
+        );
+        let pipeline = world.resource::<PipelineCache>().queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("gpu_forces_pipeline".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: FORCES_SHADER,
+            shader_defs: Vec::new(),
+            entry_point: "main".into(),
+            zero_initialize_workgroup_memory: false,
+        });
+        Self { layout, pipeline }
+    }
+}
+
+#[derive(Resource)]
+struct GpuForcesBindGroup(BindGroup);
+
+/// A system to bind this frame's buffers to the pipeline. Buffers are replaced whenever they are
+/// uploaded to, so the bind group is made again every frame.
+fn prepare_bind_group(
+    mut commands: Commands,
+    pipeline: Res<GpuForcesPipeline>,
+    render_device: Res<RenderDevice>,
+    gpu_force_buffers: Option<Res<GpuForceBuffers>>,
+    storage_buffers: Res<RenderAssets<GpuShaderStorageBuffer>>,
+) {
+    let buffers = gpu_force_buffers.and_then(|gpu_force_buffers| {
+        Some((
+            storage_buffers.get(&gpu_force_buffers.params)?,
+            storage_buffers.get(&gpu_force_buffers.bodies)?,
+            storage_buffers.get(&gpu_force_buffers.accelerations)?,
+        ))
+    });
+    let Some((params, bodies, accelerations)) = buffers else {
+        commands.remove_resource::<GpuForcesBindGroup>();
+        return;
+    };
+
+    let bind_group = render_device.create_bind_group(
+        "gpu_forces_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            params.buffer.as_entire_buffer_binding(),
+            bodies.buffer.as_entire_buffer_binding(),
+            accelerations.buffer.as_entire_buffer_binding(),
+        )),
+    );
+    commands.insert_resource(GpuForcesBindGroup(bind_group));
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuForcesLabel;
+
+/// The render graph node dispatching the compute shader, one invocation per body.
+struct GpuForcesNode;
+
+impl render_graph::Node for GpuForcesNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let (Some(gpu_force_buffers), Some(bind_group)) =
+            (world.get_resource::<GpuForceBuffers>(), world.get_resource::<GpuForcesBindGroup>())
+        else {
+            return Ok(());
+        };
+        if !gpu_force_buffers.enabled || gpu_force_buffers.body_count == 0 {
+            return Ok(());
+        }
+        // The pipeline compiles in the background, and nothing runs until it is ready.
+        let pipeline = world.resource::<GpuForcesPipeline>();
+        let Some(compute_pipeline) = world.resource::<PipelineCache>().get_compute_pipeline(pipeline.pipeline) else {
+            return Ok(());
+        };
+
+        let mut pass = render_context.command_encoder().begin_compute_pass(&ComputePassDescriptor {
+            label: Some("gpu_forces_pass"),
+            ..default()
+        });
+        pass.set_bind_group(0, &bind_group.0, &[]);
+        pass.set_pipeline(compute_pipeline);
+        pass.dispatch_workgroups(gpu_force_buffers.body_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::{time::TimeUpdateStrategy, window::ExitCondition, winit::WinitPlugin};
+    use crate::bodies::{gravity_acceleration, repulsion_accelerations};
+    use super::*;
+
+    #[test]
+    fn stale_gpu_results_fall_back_to_the_cpu() {
+        let mut world = World::new();
+        world.insert_resource(PhysicsSettings { gpu_forces: true, ..default() });
+        world.init_resource::<GpuForcesReady>();
+        let ready = |world: &mut World, frame, latest_frame, latest: Vec<(Entity, Vec3)>| {
+            world.insert_resource(GpuAccelerations { frame, pending: VecDeque::new(), latest, latest_frame });
+            world.run_system_cached(check_gpu_accelerations).unwrap();
+            world.resource::<GpuForcesReady>().0
+        };
+        let results = vec![(Entity::from_raw(0), Vec3::X)];
+
+        // Nothing read back yet, results from the last tick, and results from two ticks ago.
+        assert!(!ready(&mut world, 1, 0, Vec::new()));
+        assert!(ready(&mut world, 5, 4, results.clone()));
+        assert!(ready(&mut world, 5, 5, results.clone()));
+        assert!(!ready(&mut world, 6, 4, results.clone()));
+
+        world.insert_resource(PhysicsSettings { gpu_forces: false, ..default() });
+        assert!(!ready(&mut world, 5, 5, results));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU, run with `cargo test -- --ignored`"]
+    fn gpu_forces_match_the_cpu() {
+        let physics_settings = PhysicsSettings { gpu_forces: true, ..default() };
+        let mut app = App::new();
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        }).disable::<WinitPlugin>())
+            .insert_resource(physics_settings.clone())
+            // Bodies are uploaded once a physics tick, so every update has to run one.
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / 64.0)))
+            .init_resource::<GpuForcesReady>()
+            .add_plugins(GpuForcesPlugin);
+
+        // A few bodies close enough to push each other, and one beyond the cutoff of the rest.
+        let star = (Vec3::new(1.0, 0.0, -1.0), 10.0);
+        app.world_mut().spawn((Star, Mass(star.1), Transform::from_translation(star.0)));
+        let bodies = [
+            (Vec3::new(5.0, 0.0, 0.0), 0.1, 1.0),
+            (Vec3::new(6.5, 1.0, 0.0), 0.4, 1.5),
+            (Vec3::new(4.0, -2.0, 3.0), 0.2, 0.5),
+            (Vec3::new(-40.0, 10.0, 5.0), 0.8, 2.0),
+        ];
+        let entities: Vec<Entity> = bodies
+            .iter()
+            .map(|&(position, mass, radius)| {
+                app.world_mut().spawn((Mass(mass), Radius(radius), Acceleration::default(), Transform::from_translation(position))).id()
+            })
+            .collect();
+
+        // The pipeline compiles and the results are read back over the first few frames.
+        app.finish();
+        app.cleanup();
+        for _ in 0..200 {
+            app.update();
+            if !app.world().resource::<GpuAccelerations>().latest.is_empty() {
+                break;
+            }
+        }
+        let latest = &app.world().resource::<GpuAccelerations>().latest;
+        assert_eq!(latest.len(), bodies.len(), "no results were read back from the GPU");
+
+        for (i, &(position, mass, radius)) in bodies.iter().enumerate() {
+            let mut expected = gravity_acceleration(position - star.0, star.1, &physics_settings);
+            for (j, &(other_position, other_mass, other_radius)) in bodies.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let offset = other_position - position;
+                if let Some([push, _]) = repulsion_accelerations(offset, mass, radius, other_mass, other_radius, &physics_settings) {
+                    expected += push;
+                }
+            }
+
+            let &(_, acceleration) = latest.iter().find(|(entity, _)| *entity == entities[i]).unwrap();
+            assert!(
+                acceleration.distance(expected) <= expected.length() * 1e-4 + 1e-5,
+                "body {i} has acceleration {acceleration} on the GPU, but {expected} on the CPU",
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Sums the pull of the star and the push of every other body on each body, one body per invocation.
+// The formulas follow `gravity_acceleration` and `repulsion_accelerations` in bodies.rs.
+
+struct Params {
+    star_position: vec3<f32>,
+    star_mass: f32,
+    gravity: f32,
+    repulsion: f32,
+    repulsion_exponent: f32,
+    force_cutoff: f32,
+    // A multiple of the combined radii to cut off at instead, when above 0.
+    relative_cutoff: f32,
+    // Half the width of the box space wraps around in, when above 0.
+    periodic_half_extent: f32,
+    min_distance: f32,
+    softening: f32,
+    body_count: u32,
+    frame: u32,
+}
+
+struct Body {
+    position: vec3<f32>,
+    mass: f32,
+    radius: f32,
+    gravity_scale: f32,
+}
+
+struct Acceleration {
+    acceleration: vec3<f32>,
+    // The upload this was calculated from, so it can be matched back up with its bodies.
+    frame: u32,
+}
+
+@group(0) @binding(0) var<storage, read> params: Params;
+@group(0) @binding(1) var<storage, read> bodies: array<Body>;
+@group(0) @binding(2) var<storage, read_write> accelerations: array<Acceleration>;
+
+// The offset between two points the shortest way round, following `PhysicsSettings::separation`.
+fn separation(from: vec3<f32>, to: vec3<f32>) -> vec3<f32> {
+    let offset = to - from;
+    if params.periodic_half_extent <= 0.0 {
+        return offset;
+    }
+    let size = params.periodic_half_extent * 2.0;
+    return offset - size * round(offset / size);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.body_count || i >= arrayLength(&accelerations) {
+        return;
+    }
+    let body = bodies[i];
+    var acceleration = vec3<f32>(0.0);
+
+    // Bodies within the cutoff push this one away, harder the larger they are.
+    for (var j = 0u; j < params.body_count; j++) {
+        if j == i {
+            continue;
+        }
+        let other = bodies[j];
+        let offset = separation(body.position, other.position);
+        let distance = length(offset);
+        let cutoff = select(params.force_cutoff, params.relative_cutoff * (body.radius + other.radius), params.relative_cutoff > 0.0);
+        if distance > cutoff {
+            continue;
+        }
+
+        var direction = vec3<f32>(0.0);
+        if distance > 0.0 {
+            direction = offset / distance;
+        }
+        let r_distance = max(distance, params.min_distance) / (body.radius + other.radius);
+        acceleration -= params.repulsion * other.mass / pow(r_distance, params.repulsion_exponent) * direction;
+    }
+
+    // The star's pull isn't an inverse-square law, so it isn't Plummer softened. Softening only
+    // rounds off the direction, so the pull fades out at the center instead of flipping there,
+    // and bodies almost on top of the star are left alone.
+    let offset = separation(params.star_position, body.position);
+    let distance = length(offset);
+    if params.star_mass > 0.0 && distance >= params.min_distance {
+        let scaled_distance = distance / 10.0;
+        let magnitude = params.gravity * params.star_mass * (1.0 + scaled_distance * scaled_distance);
+        let softened_distance = sqrt(dot(offset, offset) + params.softening * params.softening);
+        acceleration += -offset / softened_distance * magnitude * body.gravity_scale;
+    }
+
+    accelerations[i] = Acceleration(acceleration, params.frame);
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use crate::bodies::Body;
+
+/// Width of each cell of the spatial grid, about the size of a few bodies.
+const GRID_CELL_SIZE: f32 = 5.0;
+
+/// The bodies sorted into cubic cells by where they were at the end of the last physics tick, so
+/// the bodies near a point can be found without checking every one of them.
+#[derive(Debug, Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<(Entity, Vec3)>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(GRID_CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    /// An empty grid with cells of the given width, which has to be positive.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Empties the grid, keeping the cells that were in use so refilling it doesn't reallocate them.
+    pub fn clear(&mut self) {
+        // Cells left empty by the last refill are dropped, so the grid doesn't grow forever as
+        // bodies wander.
+        self.cells.retain(|_, entries| !entries.is_empty());
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec3) {
+        self.cells.entry(self.cell(position)).or_default().push((entity, position));
+    }
+
+    /// The entities within the radius of a position, including any at the position itself. Only the
+    /// cells overlapping the radius are checked, so this is quick as long as the radius isn't many
+    /// cells wide.
+    pub fn within(&self, position: Vec3, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        let min = self.cell(position - Vec3::splat(radius));
+        let max = self.cell(position + Vec3::splat(radius));
+        let radius_squared = radius * radius;
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z))))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |(_, other)| other.distance_squared(position) <= radius_squared)
+            .map(|&(entity, _)| entity)
+    }
+
+    fn cell(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+}
+
+/// A system to sort the bodies into the spatial grid again once they have moved.
+pub fn update_spatial_grid(mut grid: ResMut<SpatialGrid>, query: Query<(Entity, &Transform), With<Body>>) {
+    grid.clear();
+    for (entity, transform) in &query {
+        grid.insert(entity, transform.translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use super::*;
+
+    #[test]
+    fn neighbors_match_checking_every_body() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let positions: Vec<(Entity, Vec3)> = (0..300)
+            .map(|index| {
+                let position = Vec3::new(
+                    rng.random_range(-30.0..30.0),
+                    rng.random_range(-30.0..30.0),
+                    rng.random_range(-30.0..30.0),
+                );
+                (Entity::from_raw(index), position)
+            })
+            .collect();
+
+        let mut grid = SpatialGrid::new(GRID_CELL_SIZE);
+        // Filling the grid twice checks that clearing it leaves nothing behind.
+        for _ in 0..2 {
+            grid.clear();
+            for &(entity, position) in &positions {
+                grid.insert(entity, position);
+            }
+        }
+
+        // Radii both within a cell and spanning several, around bodies and empty points alike.
+        for radius in [0.5, 3.0, 5.0, 12.0] {
+            for center in positions.iter().map(|&(_, position)| position).take(20).chain([Vec3::ZERO, Vec3::splat(-29.0)]) {
+                let mut found: Vec<Entity> = grid.within(center, radius).collect();
+                let mut expected: Vec<Entity> = positions
+                    .iter()
+                    .filter(|(_, position)| position.distance_squared(center) <= radius * radius)
+                    .map(|&(entity, _)| entity)
+                    .collect();
+                found.sort();
+                expected.sort();
+                assert_eq!(found, expected, "neighbors within {radius} of {center}");
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::Write;
+use bevy::{prelude::*, window::PrimaryWindow};
+use crate::{
+    bindings::KeyBindings,
+    bodies::{Body, LastPos, Mass, PhysicsSettings, Radius, SimulationParams},
+    picking::pick_body,
+};
+
+/// How far the tooltip sits below and to the right of the cursor, in pixels.
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+
+/// Marker for the tooltip showing the stats of the body under the cursor.
+#[derive(Component)]
+struct HoverInfo;
+
+pub struct HoverPlugin;
+
+impl Plugin for HoverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_hover_info)
+            .add_systems(Update, update_hover_info);
+    }
+}
+
+/// A system to spawn the tooltip, hidden until a body is hovered. The same node is moved and
+/// rewritten from then on, rather than a new one spawning for each body.
+fn setup_hover_info(mut commands: Commands) {
+    commands.spawn((
+        HoverInfo,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+    ));
+}
+
+/// A system to show the live stats of the body under the cursor next to it, or hide them when
+/// there isn't one. The nearest body is the one reported when several overlap under the cursor.
+/// The tooltip hides while orbiting or panning, so it doesn't trail after the drag.
+#[allow(clippy::too_many_arguments)]
+fn update_hover_info(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    key_bindings: Res<KeyBindings>,
+    time: Res<Time<Fixed>>,
+    physics_settings: Res<PhysicsSettings>,
+    params: Res<SimulationParams>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&GlobalTransform, &Projection), With<Camera>>,
+    mut hover_info: Single<(&mut Text, &mut Node, &mut Visibility), With<HoverInfo>>,
+    pickable: Query<(Entity, &GlobalTransform, &Radius), With<Body>>,
+    bodies: Query<(&Transform, &LastPos, &Mass, &Radius), With<Body>>,
+) {
+    let dragging = mouse_input.any_pressed([key_bindings.orbit_button, key_bindings.pan_button]);
+    let cursor = window.cursor_position().filter(|_| !dragging);
+
+    let (camera_transform, projection) = *camera;
+    let hit = cursor
+        .and_then(|cursor| pick_body(camera_transform, projection, window.size(), cursor, &pickable))
+        .and_then(|entity| bodies.get(entity).ok());
+
+    let (text, node, visibility) = &mut *hover_info;
+    let (Some(cursor), Some((transform, last_pos, mass, radius))) = (cursor, hit) else {
+        if **visibility != Visibility::Hidden {
+            **visibility = Visibility::Hidden;
+        }
+        return;
+    };
+
+    // Verlet integration keeps no velocity, so it is recovered from the last physics step.
+    let step = physics_settings.substep(time.timestep()).as_secs_f32();
+    let speed = (transform.translation - last_pos.0).length() / step;
+    let distance = transform.translation.distance(params.gravity_center);
+
+    // Rewrite the existing string in place so its allocation is reused every frame.
+    text.0.clear();
+    let _ = write!(
+        text.0,
+        "Mass: {:.3}\nRadius: {:.2}\nSpeed: {speed:.2}\nDistance from center: {distance:.1}",
+        mass.0, radius.0,
+    );
+    let position = cursor + TOOLTIP_OFFSET;
+    if node.left != Val::Px(position.x) || node.top != Val::Px(position.y) {
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+    }
+    if **visibility != Visibility::Inherited {
+        **visibility = Visibility::Inherited;
+    }
+}
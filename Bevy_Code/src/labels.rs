@@ -0,0 +1,105 @@
+use std::{collections::HashSet, fmt::Write};
+use bevy::prelude::*;
+use crate::{bindings::KeyBindings, bodies::{Body, Mass, Radius}, camera::CameraSettings};
+
+/// Settings for the labels floating over bodies.
+#[derive(Debug, Resource)]
+pub struct LabelSettings {
+    pub enabled: bool,
+    /// Only bodies within this distance of the camera target are labeled, to limit clutter.
+    pub range: f32,
+}
+
+impl Default for LabelSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            range: 15.0,
+        }
+    }
+}
+
+/// A text node labeling the body it holds.
+#[derive(Component)]
+struct BodyLabel(Entity);
+
+pub struct LabelsPlugin;
+
+impl Plugin for LabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LabelSettings>()
+            .add_systems(Update, (toggle_labels, update_labels).chain());
+    }
+}
+
+/// A system to show or hide the body labels.
+fn toggle_labels(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut label_settings: ResMut<LabelSettings>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_labels) {
+        label_settings.enabled = !label_settings.enabled;
+    }
+}
+
+/// A system to label each body in range with its index and mass, following it across the screen.
+/// Labels are removed once their body despawns or leaves the range.
+fn update_labels(
+    mut commands: Commands,
+    label_settings: Res<LabelSettings>,
+    camera_settings: Res<CameraSettings>,
+    camera: Single<(&Camera, &GlobalTransform)>,
+    bodies: Query<(Entity, &Mass, &Radius, &GlobalTransform), With<Body>>,
+    mut labels: Query<(Entity, &BodyLabel, &mut Node, &mut Text, &mut Visibility)>,
+) {
+    let (camera, camera_transform) = *camera;
+    let in_range = |transform: &GlobalTransform| {
+        label_settings.enabled
+            && transform.translation().distance(camera_settings.target) <= label_settings.range
+    };
+
+    let mut labeled = HashSet::new();
+    for (label_entity, label, mut node, mut text, mut visibility) in &mut labels {
+        let body = bodies.get(label.0).ok().filter(|(_, _, _, transform)| in_range(transform));
+        let Some((_, mass, radius, transform)) = body else {
+            commands.entity(label_entity).despawn();
+            continue;
+        };
+        labeled.insert(label.0);
+
+        // Screen space labels always face the camera. They float just above the
+        // body, and hide while it is behind the camera.
+        let anchor = transform.translation() + camera_transform.up() * radius.0;
+        match camera.world_to_viewport(camera_transform, anchor) {
+            Ok(position) => {
+                node.left = Val::Px(position.x);
+                node.top = Val::Px(position.y);
+                *visibility = Visibility::Inherited;
+            }
+            Err(_) => *visibility = Visibility::Hidden,
+        }
+
+        text.0.clear();
+        let _ = write!(text.0, "#{} m={:.3}", label.0.index(), mass.0);
+    }
+
+    // New labels start hidden and are placed the next frame.
+    for (entity, _, _, transform) in &bodies {
+        if in_range(transform) && !labeled.contains(&entity) {
+            commands.spawn((
+                BodyLabel(entity),
+                Text::new(""),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                Node {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                Visibility::Hidden,
+            ));
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! The N-body physics of the simulation, as Bevy plugins that can be added to any app without the
+//! camera, windowing, or user interface. `BodiesPlugin` spawns and simulates the bodies, and the
+//! components and resources it works with are public to query and tune from outside. Nothing here
+//! reads the keyboard or lights the star, which is left to the app driving the simulation. Bodies
+//! are given mesh and material handles as they spawn, so apps without rendering still need the
+//! `Mesh` and `StandardMaterial` asset types registered, and everything else the plugin sets up
+//! itself.
+
+pub mod bodies;
+pub mod coloring;
+pub mod gpu_forces;
+pub mod grid;
+pub mod materials;
+pub mod persistence;
+pub mod scenario;
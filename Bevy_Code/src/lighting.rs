@@ -0,0 +1,247 @@
+use std::{f32::consts::TAU, ops::RangeInclusive};
+use bevy::{pbr::{light_consts, PointLightShadowMap}, prelude::*};
+use crate::{bindings::KeyBindings, bodies::Star};
+
+/// Brightness the ambient light is kept within. Much brighter washes out the star's shading entirely.
+pub const AMBIENT_BRIGHTNESS_RANGE: RangeInclusive<f32> = 0.0..=1000.0;
+/// How much each press of the dim or brighten keys changes the ambient brightness by.
+const AMBIENT_BRIGHTNESS_STEP: f32 = 10.0;
+/// How fast the sun turns while its keys are held, in radians a second.
+const SUN_TURN_SPEED: f32 = 1.0;
+
+/// What lights the bodies, besides the ambient light. Only one is ever on at a time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LightingMode {
+    /// A point light inside the star, brightest near the center.
+    #[default]
+    Star,
+    /// A distant directional light, lighting the whole cloud evenly from one side.
+    Sun,
+}
+
+/// Lighting settings that can be changed during runtime.
+#[derive(Debug, Resource)]
+pub struct LightingSettings {
+    /// Kept low so the star does most of the lighting, but high enough that
+    /// the far sides of the bodies aren't pure black.
+    pub ambient_brightness: f32,
+    /// Whether the star's light casts shadows, which is expensive with many bodies.
+    pub shadows_enabled: bool,
+    /// Width in texels of each face of the star's shadow map. Larger gives sharper shadows of
+    /// smaller bodies, at the cost of memory and time.
+    pub shadow_map_size: usize,
+    /// How bright the star's light is, in lumens.
+    pub star_light_intensity: f32,
+    /// How far the star's light reaches. Bodies beyond it are only lit by the ambient light.
+    pub star_light_range: f32,
+    pub lighting_mode: LightingMode,
+    /// How bright the sun is, in lux.
+    pub sun_illuminance: f32,
+    /// Compass direction the sunlight comes from, in radians about the vertical.
+    pub sun_yaw: f32,
+    /// How far above the horizontal plane the sunlight comes from, in radians.
+    pub sun_elevation: f32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        Self {
+            ambient_brightness: 20.0,
+            shadows_enabled: false,
+            shadow_map_size: 1024,
+            star_light_intensity: 10_000_000.0,
+            star_light_range: 200.0,
+            lighting_mode: LightingMode::Star,
+            sun_illuminance: light_consts::lux::AMBIENT_DAYLIGHT,
+            sun_yaw: 0.8,
+            sun_elevation: 0.6,
+        }
+    }
+}
+
+/// Marker for the light the star shines on the bodies with.
+#[derive(Component, Default)]
+#[require(PointLight)]
+pub struct StarLight;
+
+/// Marker for the directional light standing in for a distant sun.
+#[derive(Component)]
+struct SunLight;
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightingSettings>()
+            .add_systems(Startup, setup_sun_light)
+            .add_systems(Update, (
+                (toggle_shadows, apply_shadows).chain(),
+                apply_shadow_map_size.run_if(resource_changed::<LightingSettings>),
+                (light_new_stars, toggle_lighting_mode, turn_sun, apply_star_light, apply_sun_light).chain(),
+                (adjust_ambient_light, apply_ambient_light.run_if(resource_changed::<LightingSettings>)).chain(),
+            ));
+    }
+}
+
+/// A system to dim or brighten the ambient light a step at a time.
+fn adjust_ambient_light(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut lighting_settings: ResMut<LightingSettings>,
+) {
+    let step = if key_input.just_pressed(key_bindings.brighten_ambient_light) {
+        AMBIENT_BRIGHTNESS_STEP
+    } else if key_input.just_pressed(key_bindings.dim_ambient_light) {
+        -AMBIENT_BRIGHTNESS_STEP
+    } else {
+        return;
+    };
+
+    lighting_settings.ambient_brightness = (lighting_settings.ambient_brightness + step)
+        .clamp(*AMBIENT_BRIGHTNESS_RANGE.start(), *AMBIENT_BRIGHTNESS_RANGE.end());
+    info!("Ambient brightness: {}", lighting_settings.ambient_brightness);
+}
+
+/// A system to set the brightness of the scene from the settings whenever they change, within
+/// the allowed range.
+fn apply_ambient_light(mut ambient_light: ResMut<AmbientLight>, lighting_settings: Res<LightingSettings>) {
+    let brightness = lighting_settings
+        .ambient_brightness
+        .clamp(*AMBIENT_BRIGHTNESS_RANGE.start(), *AMBIENT_BRIGHTNESS_RANGE.end());
+    if ambient_light.brightness != brightness {
+        ambient_light.brightness = brightness;
+    }
+}
+
+/// A system to resize the point light shadow maps to match the settings.
+fn apply_shadow_map_size(lighting_settings: Res<LightingSettings>, mut shadow_map: ResMut<PointLightShadowMap>) {
+    let size = lighting_settings.shadow_map_size.max(1);
+    if shadow_map.size != size {
+        shadow_map.size = size;
+    }
+}
+
+/// A function to add the sun, which stays hidden until the lighting is switched over to it.
+fn setup_sun_light(mut commands: Commands) {
+    commands.spawn((SunLight, DirectionalLight::default(), Visibility::Hidden));
+}
+
+/// A system to switch between lighting by the star and by the sun.
+fn toggle_lighting_mode(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut lighting_settings: ResMut<LightingSettings>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_sun_light) {
+        lighting_settings.lighting_mode = match lighting_settings.lighting_mode {
+            LightingMode::Star => LightingMode::Sun,
+            LightingMode::Sun => LightingMode::Star,
+        };
+        info!("Lighting: {:?}", lighting_settings.lighting_mode);
+    }
+}
+
+/// A system to turn the direction the sunlight comes from while the sun keys are held.
+fn turn_sun(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    time: Res<Time<Real>>,
+    mut lighting_settings: ResMut<LightingSettings>,
+) {
+    let mut turn = 0.0;
+    if key_input.pressed(key_bindings.turn_sun_left) {
+        turn += 1.0;
+    }
+    if key_input.pressed(key_bindings.turn_sun_right) {
+        turn -= 1.0;
+    }
+    // Only write when the keys are held, so the settings aren't marked changed every frame.
+    if turn != 0.0 {
+        lighting_settings.sun_yaw = (lighting_settings.sun_yaw + turn * SUN_TURN_SPEED * time.delta_secs()).rem_euclid(TAU);
+    }
+}
+
+/// A system to point the sun and set its brightness from the settings, showing it only while the
+/// lighting comes from the sun.
+fn apply_sun_light(
+    lighting_settings: Res<LightingSettings>,
+    mut sun: Single<(&mut DirectionalLight, &mut Transform, &mut Visibility), With<SunLight>>,
+) {
+    let (light, transform, visibility) = &mut *sun;
+    if light.illuminance != lighting_settings.sun_illuminance {
+        light.illuminance = lighting_settings.sun_illuminance;
+    }
+    let rotation = sun_rotation(lighting_settings.sun_yaw, lighting_settings.sun_elevation);
+    if transform.rotation != rotation {
+        transform.rotation = rotation;
+    }
+    visibility.set_if_neq(match lighting_settings.lighting_mode {
+        LightingMode::Star => Visibility::Hidden,
+        LightingMode::Sun => Visibility::Inherited,
+    });
+}
+
+/// A function to find the rotation that points a directional light at the origin from the given
+/// compass direction and elevation. Directional lights shine along their forward direction.
+fn sun_rotation(yaw: f32, elevation: f32) -> Quat {
+    Quat::from_euler(EulerRot::YXZ, yaw, -elevation, 0.0)
+}
+
+/// A system to give each star a light as it spawns, which the library leaves to the app.
+fn light_new_stars(mut commands: Commands, stars: Query<Entity, Added<Star>>) {
+    for star in &stars {
+        // The light's brightness and reach come from the lighting settings.
+        commands.entity(star).with_child(StarLight);
+    }
+}
+
+/// A system to keep the star's light as bright and far reaching as the settings say, including
+/// when the star is respawned, and to show it only while the lighting comes from the star.
+fn apply_star_light(
+    lighting_settings: Res<LightingSettings>,
+    mut lights: Query<(&mut PointLight, &mut Visibility), With<StarLight>>,
+) {
+    for (mut light, mut visibility) in &mut lights {
+        visibility.set_if_neq(match lighting_settings.lighting_mode {
+            LightingMode::Star => Visibility::Inherited,
+            LightingMode::Sun => Visibility::Hidden,
+        });
+        if light.intensity != lighting_settings.star_light_intensity {
+            light.intensity = lighting_settings.star_light_intensity;
+        }
+        if light.range != lighting_settings.star_light_range {
+            light.range = lighting_settings.star_light_range;
+        }
+    }
+}
+
+/// A system to turn shadows on and off.
+fn toggle_shadows(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut lighting_settings: ResMut<LightingSettings>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_shadows) {
+        lighting_settings.shadows_enabled = !lighting_settings.shadows_enabled;
+        info!("Shadows: {}", lighting_settings.shadows_enabled);
+    }
+}
+
+/// A system to keep every light casting shadows or not, including ones spawned later.
+fn apply_shadows(
+    lighting_settings: Res<LightingSettings>,
+    mut point_lights: Query<&mut PointLight>,
+    mut directional_lights: Query<&mut DirectionalLight>,
+) {
+    // Compare first so lights are only marked changed when the setting actually differs.
+    for mut light in &mut point_lights {
+        if light.shadows_enabled != lighting_settings.shadows_enabled {
+            light.shadows_enabled = lighting_settings.shadows_enabled;
+        }
+    }
+    for mut light in &mut directional_lights {
+        if light.shadows_enabled != lighting_settings.shadows_enabled {
+            light.shadows_enabled = lighting_settings.shadows_enabled;
+        }
+    }
+}
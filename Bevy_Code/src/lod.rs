@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use crate::bodies::{Body, BodyMeshes, Radius};
+
+/// Settings for swapping body meshes with how large they appear.
+#[derive(Debug, Resource)]
+pub struct LodSettings {
+    /// Apparent sizes, as radius over distance from the camera, below which bodies
+    /// drop to the next coarser mesh. Ordered from the most to the least detailed.
+    pub thresholds: [f32; 2],
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            thresholds: [0.05, 0.015],
+        }
+    }
+}
+
+pub struct LodPlugin;
+
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LodSettings>()
+            .add_systems(Update, select_body_meshes);
+    }
+}
+
+/// A system to give each body a mesh detailed enough for how large it appears on screen.
+fn select_body_meshes(
+    lod_settings: Res<LodSettings>,
+    body_meshes: Option<Res<BodyMeshes>>,
+    camera_transform: Single<&GlobalTransform, With<Camera>>,
+    mut query: Query<(&mut Mesh3d, &Radius, &GlobalTransform), With<Body>>,
+) {
+    let Some(body_meshes) = body_meshes else {
+        return;
+    };
+
+    for (mut mesh, radius, transform) in &mut query {
+        let distance = transform.translation().distance(camera_transform.translation());
+        let apparent_size = radius.0 / distance.max(f32::EPSILON);
+        let level = lod_settings
+            .thresholds
+            .iter()
+            .take_while(|&&threshold| apparent_size < threshold)
+            .count();
+
+        // The meshes are only ever shared handles, so swapping never creates or drops an asset.
+        // Comparing first keeps unchanged bodies from being marked as changed.
+        if mesh.0 != body_meshes.0[level] {
+            mesh.0 = body_meshes.0[level].clone();
+        }
+    }
+}
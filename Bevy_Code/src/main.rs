@@ -1,15 +1,108 @@
-mod bodies;
+mod background;
+mod bench;
+mod bindings;
+mod bodies_input;
 mod camera;
+mod cli;
+mod control_panel;
+mod debug_draw;
+mod hover;
+mod labels;
+mod lighting;
+mod lod;
+mod overlay;
+mod picking;
+mod presets;
+mod screenshot;
+mod selection;
+mod trails;
 
-use bevy::prelude::*;
-use bodies::BodiesPlugin;
+// The physics and everything it needs live in the library, so they can be used without the
+// camera and windowing. Importing the modules here lets the rest of the binary reach them as before.
+use minima_moralia::{bodies, coloring, gpu_forces, grid, materials, persistence, scenario};
+
+use std::process;
+use bevy::{diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin}, prelude::*};
+use bevy_egui::EguiPlugin;
+use background::BackgroundPlugin;
+use bindings::BindingsPlugin;
+use bodies::{BodiesPlugin, InitialScene, SimSeed};
+use bodies_input::BodiesInputPlugin;
 use camera::CameraPlugin;
+use clap::Parser;
+use cli::Cli;
+use coloring::ColoringPlugin;
+use control_panel::ControlPanelPlugin;
+use debug_draw::DebugDrawPlugin;
+use gpu_forces::GpuForcesPlugin;
+use hover::HoverPlugin;
+use labels::LabelsPlugin;
+use lighting::LightingPlugin;
+use lod::LodPlugin;
+use overlay::OverlayPlugin;
+use presets::PresetsPlugin;
+use scenario::{load_scenario, InitialScenario};
+use screenshot::ScreenshotPlugin;
+use selection::SelectionPlugin;
+use trails::TrailsPlugin;
 
 
 fn main() {
-    App::new()
+    // Invalid arguments and `--help` are reported by clap, which exits before anything starts.
+    let cli = Cli::parse();
+    let params = cli.simulation_params().unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        process::exit(1);
+    });
+    let camera_config = cli.camera_config().unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        process::exit(1);
+    });
+
+    if let Some(ticks) = cli.headless_ticks() {
+        bench::run(ticks, params, cli.physics_settings(), SimSeed(cli.seed));
+        return;
+    }
+    let scenario = cli.scenario.as_deref().map(load_scenario).transpose().unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        process::exit(1);
+    });
+
+    let mut app = App::new();
+    // Inserted before the plugins so they don't initialize their defaults over them.
+    app.insert_resource(params)
+        .insert_resource(cli.physics_settings())
+        .insert_resource(SimSeed(cli.seed))
+        .insert_resource(camera_config)
         .add_plugins(DefaultPlugins)
+        .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_plugins(EntityCountDiagnosticsPlugin)
+        .add_plugins(EguiPlugin { enable_multipass_for_primary_context: true })
+        .add_plugins(BackgroundPlugin)
+        .add_plugins(BindingsPlugin)
         .add_plugins(BodiesPlugin)
+        .add_plugins(BodiesInputPlugin)
         .add_plugins(CameraPlugin)
-        .run();
+        .add_plugins(ColoringPlugin)
+        .add_plugins(ControlPanelPlugin)
+        .add_plugins(DebugDrawPlugin)
+        .add_plugins(GpuForcesPlugin)
+        .add_plugins(HoverPlugin)
+        .add_plugins(LabelsPlugin)
+        .add_plugins(LightingPlugin)
+        .add_plugins(LodPlugin)
+        .add_plugins(OverlayPlugin)
+        .add_plugins(PresetsPlugin)
+        .add_plugins(ScreenshotPlugin)
+        .add_plugins(SelectionPlugin)
+        .add_plugins(TrailsPlugin);
+
+    if let Some(path) = cli.scene {
+        app.insert_resource(InitialScene(path));
+    }
+    if let Some(bodies) = scenario {
+        app.insert_resource(InitialScenario(bodies));
+    }
+
+    app.run();
 }
\ No newline at end of file
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+
+/// Body materials, either unique to each body or shared from a small palette of colors.
+/// Sharing lets the renderer batch bodies together, at the cost of exact colors.
+#[derive(Debug, Resource)]
+pub struct MaterialPalette {
+    pub shared: bool,
+    /// Number of levels each color channel is rounded to when sharing, so at most levels³ materials.
+    pub levels: u8,
+    handles: HashMap<[u8; 3], Handle<StandardMaterial>>,
+}
+
+impl Default for MaterialPalette {
+    fn default() -> Self {
+        Self {
+            shared: false,
+            levels: 4,
+            handles: HashMap::new(),
+        }
+    }
+}
+
+impl MaterialPalette {
+    /// Returns a material showing the color, shared with other bodies of a similar color when the palette is on.
+    pub fn material(&mut self, materials: &mut Assets<StandardMaterial>, color: Color) -> Handle<StandardMaterial> {
+        if !self.shared {
+            return materials.add(color);
+        }
+
+        let steps = f32::from(self.levels.max(2) - 1);
+        let color = color.to_srgba();
+        let key = [color.red, color.green, color.blue].map(|channel| (channel.clamp(0.0, 1.0) * steps).round() as u8);
+        self.handles
+            .entry(key)
+            .or_insert_with(|| {
+                let [red, green, blue] = key.map(|level| f32::from(level) / steps);
+                materials.add(Color::srgb(red, green, blue))
+            })
+            .clone()
+    }
+}
+
+/// A function to show a color on a body, through its own material or a shared one from the palette.
+pub fn paint_body(
+    material: &mut MeshMaterial3d<StandardMaterial>,
+    color: Color,
+    materials: &mut Assets<StandardMaterial>,
+    palette: &mut MaterialPalette,
+) {
+    if palette.shared {
+        let handle = palette.material(materials, color);
+        // Comparing first keeps bodies that stay in the same palette entry from being marked changed.
+        if material.0 != handle {
+            material.0 = handle;
+        }
+    } else if let Some(material) = materials.get_mut(&material.0) {
+        material.base_color = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use super::*;
+
+    #[test]
+    fn shared_palette_bounds_the_material_count() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let colors: Vec<Color> = (0..165)
+            .map(|_| Color::srgb(rng.random_range(0.5..1.0), rng.random_range(0.5..1.0), rng.random_range(0.5..1.0)))
+            .collect();
+
+        let count_materials = |shared: bool| {
+            let mut materials = Assets::<StandardMaterial>::default();
+            let mut palette = MaterialPalette {
+                shared,
+                ..default()
+            };
+            for &color in &colors {
+                let _ = palette.material(&mut materials, color);
+            }
+            materials.len()
+        };
+
+        let unique = count_materials(false);
+        let shared = count_materials(true);
+        assert_eq!(unique, colors.len());
+        assert!(shared <= usize::from(MaterialPalette::default().levels).pow(3), "{shared} shared materials");
+        assert!(shared < unique, "{shared} shared vs {unique} unique materials");
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+use bevy::{
+    diagnostic::{DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use crate::{
+    bindings::KeyBindings,
+    bodies::{angular_momentum, kinetic_energy, Body, LastPos, Mass, PhysicsSettings, SimulationParams},
+    coloring::{ColorMode, MassRange},
+};
+
+/// Seconds between refreshes of the performance overlay, so the numbers are steady enough to read.
+const PERFORMANCE_REFRESH_INTERVAL: f64 = 0.25;
+
+/// Marker for the text node showing performance statistics.
+#[derive(Component)]
+struct StatsOverlay;
+
+/// Marker for the text node showing how fast frames are rendered and how many entities there are.
+#[derive(Component)]
+struct PerformanceOverlay;
+
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_overlay)
+            .add_systems(Update, (toggle_overlay, update_overlay))
+            .add_systems(Update, (toggle_performance_overlay, update_performance_overlay).chain());
+    }
+}
+
+/// A system to spawn the overlay text in the top left corner.
+fn setup_overlay(mut commands: Commands) {
+    commands.spawn((
+        StatsOverlay,
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+    ));
+    // The performance overlay sits in the opposite corner, hidden until it is asked for.
+    commands.spawn((
+        PerformanceOverlay,
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// A system to show or hide the overlay.
+fn toggle_overlay(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut overlay: Single<&mut Visibility, With<StatsOverlay>>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_overlay) {
+        **overlay = match **overlay {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+/// A system to show or hide the performance overlay.
+fn toggle_performance_overlay(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut overlay: Single<&mut Visibility, With<PerformanceOverlay>>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_performance_overlay) {
+        **overlay = match **overlay {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+/// A system to refresh the performance overlay with the smoothed FPS, frame time, and entity
+/// count a few times a second. Once shown again, it refreshes straight away rather than waiting.
+fn update_performance_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    time: Res<Time<Real>>,
+    mut last_refresh: Local<Option<f64>>,
+    mut overlay: Single<(&mut Text, &Visibility), With<PerformanceOverlay>>,
+) {
+    let (text, visibility) = &mut *overlay;
+    if **visibility == Visibility::Hidden {
+        *last_refresh = None;
+        return;
+    }
+    let now = time.elapsed_secs_f64();
+    if last_refresh.is_some_and(|last_refresh| now - last_refresh < PERFORMANCE_REFRESH_INTERVAL) {
+        return;
+    }
+    *last_refresh = Some(now);
+
+    let smoothed = |path: &DiagnosticPath| diagnostics.get(path).and_then(|diagnostic| diagnostic.smoothed()).unwrap_or_default();
+    let fps = smoothed(&FrameTimeDiagnosticsPlugin::FPS);
+    let frame_time = smoothed(&FrameTimeDiagnosticsPlugin::FRAME_TIME);
+    let entities = diagnostics
+        .get(&EntityCountDiagnosticsPlugin::ENTITY_COUNT)
+        .and_then(|entities| entities.value())
+        .unwrap_or_default();
+
+    text.0.clear();
+    let _ = write!(text.0, "FPS: {fps:.0}\nFrame time: {frame_time:.2} ms\nEntities: {entities:.0}");
+}
+
+/// A system to refresh the overlay with the smoothed FPS, the number of bodies, their kinetic
+/// energy and angular momentum about the center of gravity, and the mass legend.
+#[allow(clippy::too_many_arguments)]
+fn update_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    params: Res<SimulationParams>,
+    color_mode: Res<ColorMode>,
+    mass_range: Res<MassRange>,
+    time: Res<Time<Fixed>>,
+    physics_settings: Res<PhysicsSettings>,
+    mut overlay: Single<(&mut Text, &Visibility), With<StatsOverlay>>,
+    bodies: Query<(&Mass, &Transform, &LastPos), With<Body>>,
+) {
+    let (text, visibility) = &mut *overlay;
+    if **visibility == Visibility::Hidden {
+        return;
+    }
+
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+
+    // Rewrite the existing string in place so its allocation is reused every frame.
+    text.0.clear();
+    let step = physics_settings.substep(time.timestep()).as_secs_f32();
+    let energy = kinetic_energy(&bodies, step);
+    let _ = write!(text.0, "FPS: {fps:.0}\nBodies: {}\nKinetic energy: {energy:.1}", bodies.iter().count());
+    let spin = angular_momentum(&bodies, step, params.gravity_center);
+    let axis = spin.normalize_or_zero();
+    let _ = write!(
+        text.0,
+        "\nAngular momentum: {:.1} about ({:.2}, {:.2}, {:.2})",
+        spin.length(),
+        axis.x,
+        axis.y,
+        axis.z,
+    );
+    if *color_mode == ColorMode::ByMass {
+        let _ = write!(text.0, "\nMass: {:.3} (blue) to {:.3} (red)", mass_range.min, mass_range.max);
+    }
+}
@@ -0,0 +1,223 @@
+use std::{error::Error, fmt::Write, fs, path::Path};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::bodies::{
+    BaseColor, BodyDescriptor, Charge, Damping, GravityScale, LastPos, Mass, PhysicsSettings, Radius, SimulationParams,
+};
+
+/// Version of the snapshot format, bumped whenever older files would be misread.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Default file the simulation state is quick saved to and loaded from.
+pub const STATE_PATH: &str = "simulation_state.json";
+/// Directory exported snapshots are written to, relative to the working directory.
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+/// A saved body, holding everything needed to recreate it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodySnapshot {
+    pub position: Vec3,
+    /// Velocity rather than last position, so snapshots don't depend on the timestep.
+    pub velocity: Vec3,
+    pub mass: f32,
+    pub radius: f32,
+    /// Color as sRGBA components.
+    pub color: [f32; 4],
+    /// Electric charge, for bodies that have one.
+    #[serde(default)]
+    pub charge: Option<f32>,
+    /// How strongly the star pulls on the body, for bodies that don't feel its full pull.
+    #[serde(default)]
+    pub gravity_scale: Option<f32>,
+    /// Damping of the body, for bodies that don't use the global damping.
+    #[serde(default)]
+    pub damping: Option<f32>,
+    /// Whether the body is held in place.
+    #[serde(default)]
+    pub anchored: bool,
+}
+
+/// A saved simulation, with the settings it was running under.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub params: SimulationParams,
+    pub physics: PhysicsSettings,
+    pub bodies: Vec<BodySnapshot>,
+}
+
+/// A row of a diagnostics export, describing one body as it was when the simulation was paused.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodyDiagnostics {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub mass: f32,
+    pub radius: f32,
+    /// Other bodies within the density coloring's neighbor radius.
+    pub neighbors: usize,
+}
+
+/// A function to write a snapshot to a JSON file.
+pub fn save_snapshot(path: &Path, snapshot: &Snapshot) -> Result<(), Box<dyn Error>> {
+    fs::write(path, serde_json::to_string_pretty(snapshot)?)?;
+    Ok(())
+}
+
+/// A function to read a snapshot from a JSON file, refusing other versions of the format.
+pub fn load_snapshot(path: &Path) -> Result<Snapshot, Box<dyn Error>> {
+    let snapshot: Snapshot = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(format!("snapshot version {} is not supported, expected {SNAPSHOT_VERSION}", snapshot.version).into());
+    }
+    Ok(snapshot)
+}
+
+/// A function to describe a body for a snapshot, recovering its velocity from its last position.
+#[allow(clippy::too_many_arguments)]
+pub fn body_snapshot(
+    transform: &Transform,
+    last_pos: &LastPos,
+    mass: &Mass,
+    radius: &Radius,
+    base_color: &BaseColor,
+    charge: Option<&Charge>,
+    gravity_scale: Option<&GravityScale>,
+    damping: Option<&Damping>,
+    anchored: bool,
+    timestep: f32,
+) -> BodySnapshot {
+    // The base color is saved rather than the material's, which may be showing the color mode.
+    let color = base_color.0.to_srgba();
+    BodySnapshot {
+        position: transform.translation,
+        velocity: (transform.translation - last_pos.0) / timestep,
+        mass: mass.0,
+        radius: radius.0,
+        color: [color.red, color.green, color.blue, color.alpha],
+        charge: charge.map(|charge| charge.0),
+        gravity_scale: gravity_scale.map(|gravity_scale| gravity_scale.0),
+        damping: damping.map(|damping| damping.0),
+        anchored,
+    }
+}
+
+/// A function to describe how to spawn a body from a snapshot, one timestep behind along its velocity.
+pub fn body_descriptor(body: &BodySnapshot, timestep: f32) -> BodyDescriptor {
+    let [red, green, blue, alpha] = body.color;
+    BodyDescriptor {
+        position: body.position,
+        last_pos: body.position - body.velocity * timestep,
+        mass: body.mass,
+        radius: body.radius,
+        color: Color::srgba(red, green, blue, alpha),
+        charge: body.charge,
+        gravity_scale: body.gravity_scale,
+        damping: body.damping,
+        anchored: body.anchored,
+    }
+}
+
+/// A function to lay out diagnostics as CSV with a header row, one body per row ordered by entity
+/// index, so exports of the same bodies line up row for row.
+pub fn diagnostics_csv(rows: &mut [BodyDiagnostics]) -> String {
+    rows.sort_by_key(|row| row.entity.index());
+    let mut csv = String::from("id,x,y,z,vx,vy,vz,mass,radius,neighbors\n");
+    for row in rows.iter() {
+        let BodyDiagnostics { entity, position: p, velocity: v, mass, radius, neighbors } = row;
+        // Writing to a string can't fail.
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{mass},{radius},{neighbors}",
+            entity.index(), p.x, p.y, p.z, v.x, v.y, v.z,
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trips_through_a_file() {
+        let timestep = 1.0 / 64.0;
+        let bodies = [
+            (Vec3::new(1.5, -2.25, 30.125), Vec3::new(1.5, -2.0, 30.0), 0.1, 1.0, Color::srgb(0.5, 0.75, 1.0)),
+            (Vec3::new(-12.0, 0.0, 4.0), Vec3::new(-12.0, 0.0, 4.0), 0.8, 2.0, Color::srgb(1.0, 0.5, 0.5)),
+        ];
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            params: SimulationParams::default(),
+            physics: PhysicsSettings::default(),
+            bodies: bodies
+                .iter()
+                .map(|&(position, last_pos, mass, radius, color)| {
+                    body_snapshot(
+                        &Transform::from_translation(position),
+                        &LastPos(last_pos),
+                        &Mass(mass),
+                        &Radius(radius),
+                        &BaseColor(color),
+                        None,
+                        None,
+                        None,
+                        false,
+                        timestep,
+                    )
+                })
+                .collect(),
+        };
+
+        let path = env::temp_dir().join(format!("snapshot_round_trip_{}.json", std::process::id()));
+        save_snapshot(&path, &snapshot).unwrap();
+        let loaded = load_snapshot(&path);
+        let _ = fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.bodies, snapshot.bodies);
+        for (body, &(position, last_pos, mass, radius, _)) in loaded.bodies.iter().zip(&bodies) {
+            let descriptor = body_descriptor(body, timestep);
+            assert_eq!(descriptor.position, position);
+            assert_eq!(descriptor.mass, mass);
+            assert_eq!(descriptor.radius, radius);
+            // Going through a velocity costs a rounding step, but no more.
+            assert!(descriptor.last_pos.distance(last_pos) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn diagnostics_are_ordered_by_entity() {
+        let row = |index, mass, neighbors| BodyDiagnostics {
+            entity: Entity::from_raw(index),
+            position: Vec3::new(1.0, -2.0, 0.5),
+            velocity: Vec3::new(0.0, 3.0, 0.0),
+            mass,
+            radius: 1.5,
+            neighbors,
+        };
+        let mut rows = [row(7, 0.25, 2), row(2, 1.0, 0), row(4, 0.5, 11)];
+        assert_eq!(diagnostics_csv(&mut rows), "\
+            id,x,y,z,vx,vy,vz,mass,radius,neighbors\n\
+            2,1,-2,0.5,0,3,0,1,1.5,0\n\
+            4,1,-2,0.5,0,3,0,0.5,1.5,11\n\
+            7,1,-2,0.5,0,3,0,0.25,1.5,2\n");
+    }
+
+    #[test]
+    fn snapshot_refuses_other_versions() {
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION + 1,
+            params: SimulationParams::default(),
+            physics: PhysicsSettings::default(),
+            bodies: Vec::new(),
+        };
+
+        let path = env::temp_dir().join(format!("snapshot_version_{}.json", std::process::id()));
+        save_snapshot(&path, &snapshot).unwrap();
+        let loaded = load_snapshot(&path);
+        let _ = fs::remove_file(&path);
+        assert!(loaded.is_err());
+    }
+}
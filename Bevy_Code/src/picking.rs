@@ -0,0 +1,118 @@
+use bevy::{prelude::*, render::camera::CameraProjection};
+use crate::bodies::{Body, Radius};
+
+/// A function to find the body under a cursor position, casting a ray from the camera through it
+/// and returning the nearest body it hits. Bodies are their mesh's size, since meshes are unit
+/// spheres scaled by the radius.
+pub fn pick_body(
+    camera_transform: &GlobalTransform,
+    projection: &Projection,
+    window_size: Vec2,
+    cursor: Vec2,
+    bodies: &Query<(Entity, &GlobalTransform, &Radius), With<Body>>,
+) -> Option<Entity> {
+    let ray = cursor_ray(camera_transform, projection, window_size, cursor)?;
+    nearest_hit(ray, bodies.iter().map(|(entity, transform, radius)| (entity, transform.translation(), radius.0)))
+}
+
+/// A function to find the ray through a cursor position, from the camera's projection and the
+/// window's size as they are right now. The camera's own cached projection only catches up with
+/// a resize later in the frame, and aiming with the old aspect ratio drifts away from the cursor.
+fn cursor_ray(camera_transform: &GlobalTransform, projection: &Projection, window_size: Vec2, cursor: Vec2) -> Option<Ray3d> {
+    if window_size.cmple(Vec2::ZERO).any() {
+        return None;
+    }
+    let mut projection = projection.clone();
+    projection.update(window_size.x, window_size.y);
+    let world_from_ndc = camera_transform.compute_matrix() * projection.get_clip_from_view().inverse();
+
+    // Cursor positions run down from the top left, while NDC runs up from the center.
+    let mut ndc = cursor / window_size * 2.0 - Vec2::ONE;
+    ndc.y = -ndc.y;
+    // Depth is reversed, and exactly 0 is infinitely far away, so the far point is just short of it.
+    let near = world_from_ndc.project_point3(ndc.extend(1.0));
+    let far = world_from_ndc.project_point3(ndc.extend(f32::EPSILON));
+    Dir3::new(far - near).ok().map(|direction| Ray3d { origin: near, direction })
+}
+
+/// A function to find the body a ray hits first, from each body's entity, position, and radius.
+/// The nearest hit wins, whichever order the bodies come in.
+fn nearest_hit(ray: Ray3d, bodies: impl IntoIterator<Item = (Entity, Vec3, f32)>) -> Option<Entity> {
+    bodies
+        .into_iter()
+        .filter_map(|(entity, position, radius)| ray_sphere_distance(ray, position, radius).map(|distance| (distance, entity)))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, entity)| entity)
+}
+
+/// A function to find how far along a ray it first meets a sphere, if it does at all.
+fn ray_sphere_distance(ray: Ray3d, center: Vec3, radius: f32) -> Option<f32> {
+    // Meshes are unit spheres scaled by the radius, so the radius is the sphere's size in the world.
+    let to_center = center - ray.origin;
+    let closest_approach = to_center.dot(*ray.direction);
+    let miss_squared = to_center.length_squared() - closest_approach * closest_approach;
+    if miss_squared > radius * radius {
+        return None;
+    }
+
+    // The near side of the sphere, or the far side if the ray starts inside it.
+    let half_chord = (radius * radius - miss_squared).sqrt();
+    [closest_approach - half_chord, closest_approach + half_chord]
+        .into_iter()
+        .find(|&distance| distance >= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::CameraSettings;
+    use super::*;
+
+    #[test]
+    fn the_nearest_sphere_is_picked() {
+        let ray = Ray3d { origin: Vec3::ZERO, direction: Dir3::NEG_Z };
+        let (near, far, beside) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+
+        // The far sphere comes first, but the nearer one along the ray is still the one picked.
+        let spheres = [(far, Vec3::new(0.0, 0.0, -20.0), 3.0), (near, Vec3::new(0.0, 0.5, -10.0), 1.0)];
+        assert_eq!(nearest_hit(ray, spheres), Some(near));
+        assert_eq!(nearest_hit(ray, spheres.into_iter().rev()), Some(near));
+
+        // A sphere off to the side is only hit once its radius reaches the ray.
+        assert_eq!(nearest_hit(ray, [(beside, Vec3::new(2.0, 0.0, -5.0), 1.5)]), None);
+        assert_eq!(nearest_hit(ray, [(beside, Vec3::new(2.0, 0.0, -5.0), 2.5)]), Some(beside));
+
+        // Spheres behind the ray are missed, while one around its origin is hit on its far side.
+        assert_eq!(nearest_hit(ray, [(far, Vec3::new(0.0, 0.0, 10.0), 1.0)]), None);
+        assert_eq!(ray_sphere_distance(ray, Vec3::ZERO, 2.0), Some(2.0));
+        assert_eq!(ray_sphere_distance(ray, Vec3::new(0.0, 0.0, -10.0), 1.0), Some(9.0));
+    }
+
+    #[test]
+    fn picking_follows_window_resizes() {
+        // Looking at the orbit target from off to one side, with a projection still set up for a
+        // square window, as it is until the camera catches up with a resize.
+        let target = CameraSettings::default().target;
+        let camera_transform = GlobalTransform::from(Transform::from_translation(target + Vec3::new(20.0, 10.0, 30.0)).looking_at(target, Vec3::Y));
+        let perspective = PerspectiveProjection::default();
+        let projection = Projection::Perspective(perspective.clone());
+        let distance = camera_transform.translation().distance(target);
+        let body = Entity::from_raw(1);
+
+        for window_size in [Vec2::new(1280.0, 720.0), Vec2::new(400.0, 900.0), Vec2::new(2560.0, 600.0)] {
+            let pick = |cursor: Vec2, position: Vec3| {
+                cursor_ray(&camera_transform, &projection, window_size, cursor)
+                    .and_then(|ray| nearest_hit(ray, [(body, position, 1.0)]))
+            };
+
+            // The center of the screen always looks straight at the target.
+            assert_eq!(pick(window_size / 2.0, target), Some(body), "missed the target in a {window_size} window");
+
+            // Near the right edge, the aspect ratio decides where on screen a body ends up.
+            let ndc_x = 0.8;
+            let aspect_ratio = window_size.x / window_size.y;
+            let position = target + *camera_transform.right() * ndc_x * distance * (perspective.fov / 2.0).tan() * aspect_ratio;
+            let cursor = Vec2::new(window_size.x * (1.0 + ndc_x) / 2.0, window_size.y / 2.0);
+            assert_eq!(pick(cursor, position), Some(body), "missed a body near the edge of a {window_size} window");
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::{collections::BTreeMap, fs};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{bindings::KeyBindings, camera::{CameraGlide, CameraSet, CameraSettings, CameraView}};
+
+/// File the presets are saved to on exit and loaded from at startup.
+const PRESETS_PATH: &str = "camera_presets.ron";
+
+/// Number keys used to address the preset slots, slot 1 first.
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Saved camera views, keyed by their slot number from 1 to 9.
+#[derive(Debug, Default, Resource, Serialize, Deserialize)]
+pub struct CameraPresets(pub BTreeMap<u8, CameraView>);
+
+pub struct PresetsPlugin;
+
+impl Plugin for PresetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_presets())
+            .add_systems(Update, store_or_recall_preset.before(CameraSet::Input))
+            .add_systems(Last, save_presets_on_exit);
+    }
+}
+
+/// A function to read the presets file, falling back to no presets if it can't be used.
+fn load_presets() -> CameraPresets {
+    // A missing file just means no presets have been saved yet.
+    let Ok(contents) = fs::read_to_string(PRESETS_PATH) else {
+        return CameraPresets::default();
+    };
+
+    ron::from_str(&contents).unwrap_or_else(|err| {
+        warn!("Ignoring unreadable camera presets in {PRESETS_PATH}: {err}");
+        CameraPresets::default()
+    })
+}
+
+/// A system to store the current view with the store modifier + number, and glide back to it with the number alone.
+fn store_or_recall_preset(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut presets: ResMut<CameraPresets>,
+    mut camera_glide: ResMut<CameraGlide>,
+    camera_settings: Res<CameraSettings>,
+    camera_transform: Single<&Transform, With<Camera>>,
+) {
+    let storing = key_input.any_pressed(key_bindings.store_preset.iter().copied());
+
+    for (slot, key) in (1..).zip(SLOT_KEYS) {
+        if !key_input.just_pressed(key) {
+            continue;
+        }
+
+        if storing {
+            presets.0.insert(slot, CameraView::capture(&camera_settings, &camera_transform));
+            info!("Stored camera preset {slot}.");
+        } else if let Some(view) = presets.0.get(&slot) {
+            camera_glide.0 = Some(*view);
+        }
+    }
+}
+
+/// A system to write the presets to disk when the app is closing.
+fn save_presets_on_exit(mut exit_events: EventReader<AppExit>, presets: Res<CameraPresets>) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+
+    let result = ron::ser::to_string_pretty(&*presets, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|contents| fs::write(PRESETS_PATH, contents).map_err(|err| err.to_string()));
+
+    if let Err(err) = result {
+        warn!("Failed to save camera presets to {PRESETS_PATH}: {err}");
+    }
+}
@@ -0,0 +1,198 @@
+use std::{fs, path::Path};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use crate::bodies::{radius_from_mass, random_color, BodyDescriptor};
+
+/// Columns a CSV scenario has to have, in any order alongside the optional ones.
+const REQUIRED_COLUMNS: [&str; 4] = ["x", "y", "z", "mass"];
+/// Columns a CSV scenario can leave out, which fall back to their defaults.
+const OPTIONAL_COLUMNS: [&str; 7] = ["vx", "vy", "vz", "r", "g", "b", "radius"];
+
+/// A body from an externally authored scenario file. Only the position and mass are needed.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioBody {
+    pub position: Vec3,
+    pub mass: f32,
+    /// Starting velocity, at rest if not given.
+    #[serde(default)]
+    pub velocity: Vec3,
+    /// Color as sRGB components, random if not given.
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    /// Radius, following from the mass if not given.
+    #[serde(default)]
+    pub radius: Option<f32>,
+}
+
+/// Bodies from a scenario file to start with instead of randomly generated ones.
+#[derive(Debug, Resource)]
+pub struct InitialScenario(pub Vec<ScenarioBody>);
+
+/// A function to read the bodies of a scenario from a CSV file with a header row, or a JSON array
+/// of bodies otherwise. Errors give the line they were found on.
+pub fn load_scenario(path: &Path) -> Result<Vec<ScenarioBody>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read the scenario file {}: {err}", path.display()))?;
+    let is_csv = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let bodies = if is_csv {
+        parse_csv(&contents)
+    } else {
+        parse_json(&contents)
+    };
+    bodies.map_err(|err| format!("cannot parse the scenario file {}: {err}", path.display()))
+}
+
+/// A function to read scenario bodies from CSV, one per row, with the columns named in the first
+/// row. Blank lines and lines starting with `#` are skipped.
+fn parse_csv(contents: &str) -> Result<Vec<ScenarioBody>, String> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let Some((header_line, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = header.split(',').map(|column| column.trim().to_lowercase()).collect();
+    for column in &columns {
+        if !REQUIRED_COLUMNS.contains(&column.as_str()) && !OPTIONAL_COLUMNS.contains(&column.as_str()) {
+            return Err(format!("line {header_line}: unknown column `{column}`"));
+        }
+    }
+    if let Some(missing) = REQUIRED_COLUMNS.iter().find(|&&required| !columns.iter().any(|column| column == required)) {
+        return Err(format!("line {header_line}: missing the `{missing}` column"));
+    }
+
+    lines
+        .map(|(line_number, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != columns.len() {
+                return Err(format!("line {line_number}: expected {} fields, found {}", columns.len(), fields.len()));
+            }
+
+            // Empty fields count as left out, so optional columns can be filled in for only some bodies.
+            let field = |name: &str| -> Result<Option<f32>, String> {
+                let Some(index) = columns.iter().position(|column| column == name) else {
+                    return Ok(None);
+                };
+                let value = fields[index];
+                if value.is_empty() {
+                    return Ok(None);
+                }
+                value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("line {line_number}: `{name}` is not a number: {value}"))
+            };
+            let required = |name: &str| {
+                field(name)?.ok_or_else(|| format!("line {line_number}: `{name}` is missing"))
+            };
+
+            let color = match (field("r")?, field("g")?, field("b")?) {
+                (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+                (None, None, None) => None,
+                _ => return Err(format!("line {line_number}: a color needs all of `r`, `g`, and `b`")),
+            };
+            let body = ScenarioBody {
+                position: Vec3::new(required("x")?, required("y")?, required("z")?),
+                mass: required("mass")?,
+                velocity: Vec3::new(
+                    field("vx")?.unwrap_or_default(),
+                    field("vy")?.unwrap_or_default(),
+                    field("vz")?.unwrap_or_default(),
+                ),
+                color,
+                radius: field("radius")?,
+            };
+            validate(&body).map_err(|err| format!("line {line_number}: {err}"))?;
+            Ok(body)
+        })
+        .collect()
+}
+
+/// A function to read scenario bodies from a JSON array. serde_json already reports the line and
+/// column of anything it can't parse, and bodies that parse but can't be simulated are reported
+/// by their place in the array.
+fn parse_json(contents: &str) -> Result<Vec<ScenarioBody>, String> {
+    let bodies: Vec<ScenarioBody> = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+    for (index, body) in bodies.iter().enumerate() {
+        validate(body).map_err(|err| format!("body {}: {err}", index + 1))?;
+    }
+    Ok(bodies)
+}
+
+/// A function to check a scenario body can actually be simulated.
+fn validate(body: &ScenarioBody) -> Result<(), String> {
+    if body.mass <= 0.0 || !body.mass.is_finite() {
+        return Err(format!("mass must be positive, found {}", body.mass));
+    }
+    if let Some(radius) = body.radius.filter(|&radius| radius <= 0.0 || !radius.is_finite()) {
+        return Err(format!("radius must be positive, found {radius}"));
+    }
+    Ok(())
+}
+
+/// A function to describe how to spawn a scenario body, one timestep behind along its velocity,
+/// filling in whatever the scenario left out.
+pub fn scenario_descriptor(body: &ScenarioBody, rng: &mut impl Rng, timestep: f32) -> BodyDescriptor {
+    BodyDescriptor {
+        position: body.position,
+        last_pos: body.position - body.velocity * timestep,
+        mass: body.mass,
+        radius: body.radius.unwrap_or_else(|| radius_from_mass(body.mass)),
+        color: body.color.map_or_else(|| random_color(rng), Color::srgb_from_array),
+        charge: None,
+        gravity_scale: None,
+        damping: None,
+        anchored: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use super::*;
+
+    #[test]
+    fn csv_scenarios_fill_in_missing_fields() {
+        let contents = "\
+            # Two bodies, one at rest with its radius from its mass.\n\
+            x, y, z, mass, vx, vy, vz, r, g, b, radius\n\
+            1.0, 2.0, 3.0, 0.8, 0.5, 0.0, -0.5, 1.0, 0.5, 0.25, 1.5\n\
+            \n\
+            -4.0, 0.0, 6.0, 0.1, , , , , , ,\n";
+        let bodies = parse_csv(contents).unwrap();
+        assert_eq!(bodies, [
+            ScenarioBody {
+                position: Vec3::new(1.0, 2.0, 3.0),
+                mass: 0.8,
+                velocity: Vec3::new(0.5, 0.0, -0.5),
+                color: Some([1.0, 0.5, 0.25]),
+                radius: Some(1.5),
+            },
+            ScenarioBody { position: Vec3::new(-4.0, 0.0, 6.0), mass: 0.1, velocity: Vec3::ZERO, color: None, radius: None },
+        ]);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let descriptor = scenario_descriptor(&bodies[1], &mut rng, 1.0 / 64.0);
+        assert_eq!(descriptor.last_pos, bodies[1].position);
+        assert!((descriptor.radius - radius_from_mass(0.1)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn scenario_errors_give_their_line() {
+        let error = |contents: &str| parse_csv(contents).unwrap_err();
+        assert_eq!(error("x,y,z\n1,2,3\n"), "line 1: missing the `mass` column");
+        assert_eq!(error("x,y,z,mass,spin\n"), "line 1: unknown column `spin`");
+        assert_eq!(error("x,y,z,mass\n1,2,3,1\n\n1,2,three,1\n"), "line 4: `z` is not a number: three");
+        assert_eq!(error("x,y,z,mass\n1,2,3\n"), "line 2: expected 4 fields, found 3");
+        assert_eq!(error("x,y,z,mass\n1,2,3,-1\n"), "line 2: mass must be positive, found -1");
+
+        // JSON errors come from serde_json, which gives the line too.
+        let json = "[\n  { \"position\": [0, 0, 0], \"mass\": 1 },\n  { \"position\": [1, 2, 3] }\n]";
+        assert!(parse_json(json).unwrap_err().contains("line 3"));
+        assert_eq!(parse_json("[{ \"position\": [0, 0, 0], \"mass\": 0 }]").unwrap_err(), "body 1: mass must be positive, found 0");
+    }
+}
@@ -0,0 +1,118 @@
+use std::{fs, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+use bevy::{
+    prelude::*,
+    render::view::screenshot::{Screenshot, ScreenshotCaptured},
+    tasks::IoTaskPool,
+    window::PrimaryWindow,
+};
+use crate::{bindings::KeyBindings, control_panel::ControlPanel};
+
+/// Directory screenshots are written to, relative to the working directory. They are kept
+/// together here rather than saved straight into the working directory, where a long session
+/// would bury everything else in `screenshot_<timestamp>.png` files.
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Settings for what screenshots show.
+#[derive(Debug, Resource)]
+pub struct ScreenshotSettings {
+    /// Whether the control panel is hidden for the frame a screenshot is taken in, so only the
+    /// simulation is captured.
+    pub hide_control_panel: bool,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        Self { hide_control_panel: true }
+    }
+}
+
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenshotSettings>()
+            .add_systems(Update, take_screenshot);
+    }
+}
+
+/// A system to save a timestamped screenshot of the primary window. With the control panel to be
+/// hidden, it is hidden first and the capture is taken the frame after, once the panel is gone.
+fn take_screenshot(
+    mut commands: Commands,
+    mut waiting_for_panel: Local<Option<PathBuf>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    screenshot_settings: Res<ScreenshotSettings>,
+    control_panel: Option<ResMut<ControlPanel>>,
+    primary_window: Query<(), With<PrimaryWindow>>,
+) {
+    if let Some(path) = waiting_for_panel.take() {
+        capture(&mut commands, path, true);
+        return;
+    }
+    if !key_input.just_pressed(key_bindings.screenshot) {
+        return;
+    }
+
+    if primary_window.is_empty() {
+        warn!("Cannot take a screenshot without a primary window.");
+        return;
+    }
+
+    if let Err(err) = fs::create_dir_all(SCREENSHOT_DIR) {
+        warn!("Cannot create the {SCREENSHOT_DIR} directory for screenshots: {err}");
+        return;
+    }
+
+    // Millisecond timestamps keep quick successive screenshots from overwriting each other.
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    let path = Path::new(SCREENSHOT_DIR).join(format!("screenshot_{timestamp}.png"));
+
+    match control_panel.filter(|panel| panel.visible && screenshot_settings.hide_control_panel) {
+        Some(mut control_panel) => {
+            control_panel.visible = false;
+            *waiting_for_panel = Some(path);
+        }
+        None => capture(&mut commands, path, false),
+    }
+}
+
+/// A function to capture the primary window, to be saved to the path once it arrives.
+fn capture(commands: &mut Commands, path: PathBuf, restore_control_panel: bool) {
+    // The capture is read back from the GPU asynchronously and saved once it arrives.
+    commands
+        .spawn(Screenshot::primary_window())
+        .observe(save_in_background(path, restore_control_panel));
+}
+
+/// A function to build an observer that encodes and writes a captured screenshot
+/// on the IO task pool, so large PNGs don't stall the frame they arrive in. The control panel is
+/// shown again once the capture is in, if it was hidden for it.
+fn save_in_background(
+    path: PathBuf,
+    restore_control_panel: bool,
+) -> impl FnMut(Trigger<ScreenshotCaptured>, Option<ResMut<ControlPanel>>) {
+    move |trigger, control_panel| {
+        if let Some(mut control_panel) = control_panel.filter(|_| restore_control_panel) {
+            control_panel.visible = true;
+        }
+
+        let image = trigger.event().0.clone();
+        let path = path.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                let result = image
+                    .try_into_dynamic()
+                    .map_err(|err| err.to_string())
+                    .and_then(|image| image.to_rgb8().save(&path).map_err(|err| err.to_string()));
+                match result {
+                    Ok(()) => info!("Saved screenshot to {}", path.display()),
+                    Err(err) => warn!("Failed to save screenshot to {}: {err}", path.display()),
+                }
+            })
+            .detach();
+    }
+}
@@ -0,0 +1,210 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::input::egui_wants_any_pointer_input;
+use crate::{
+    bindings::KeyBindings,
+    bodies::{Anchored, Body, LastPos, Radius, Spring, SpringSettings},
+    camera::{CameraFollow, CameraGlide, CameraSet, CameraSettings},
+    picking::pick_body,
+};
+
+/// Marker for bodies that have been selected by clicking on them.
+#[derive(Component)]
+pub struct Selected;
+
+/// Settings for telling clicks on bodies apart from drags and double-clicks.
+#[derive(Debug, Resource)]
+pub struct SelectionSettings {
+    /// Longest time between two clicks on the same body, in seconds, for them to be a double-click.
+    pub double_click_window: f32,
+    /// Furthest the cursor can move between press and release, in pixels, for it to be a click.
+    /// Moving further is a drag, which pans the camera rather than selecting.
+    pub click_tolerance: f32,
+    /// Color of the ring drawn around each selected body.
+    pub highlight_color: Color,
+    /// Size of the ring around a selected body, as a multiple of the body's radius.
+    pub highlight_scale: f32,
+}
+
+impl Default for SelectionSettings {
+    fn default() -> Self {
+        Self {
+            double_click_window: 0.3,
+            click_tolerance: 4.0,
+            highlight_color: Color::srgb(1.0, 0.85, 0.2),
+            highlight_scale: 1.3,
+        }
+    }
+}
+
+/// Where the select button went down, and the last body clicked and when.
+#[derive(Default)]
+struct ClickTracker {
+    press_position: Option<Vec2>,
+    last_click: Option<(Entity, f64)>,
+}
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectionSettings>()
+            // Clicks retarget the camera, so they come before it is moved to make that the same frame.
+            .add_systems(Update, click_bodies.before(CameraSet::Input).run_if(not(egui_wants_any_pointer_input)))
+            .add_systems(Update, (link_selected_bodies, toggle_anchor))
+            .add_systems(Update, draw_selection_highlights.after(CameraSet::Apply));
+    }
+}
+
+/// A system to select the body clicked on, and recenter the orbit camera on a body that is double-clicked.
+/// Clicking empty space clears the selection, and holding the extend selection keys adds or
+/// removes the body clicked on instead of replacing the selection. Holding the follow keys has the
+/// camera follow the body clicked on, and clicking empty space stops following.
+#[allow(clippy::too_many_arguments)]
+fn click_bodies(
+    mut commands: Commands,
+    mut tracker: Local<ClickTracker>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selection_settings: Res<SelectionSettings>,
+    mut camera_settings: ResMut<CameraSettings>,
+    mut camera_glide: ResMut<CameraGlide>,
+    mut camera_follow: ResMut<CameraFollow>,
+    time: Res<Time<Real>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&GlobalTransform, &Projection), With<Camera>>,
+    bodies: Query<(Entity, &GlobalTransform, &Radius), With<Body>>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    let cursor = window.cursor_position();
+    if mouse_input.just_pressed(key_bindings.select_button) {
+        tracker.press_position = cursor;
+    }
+    if !mouse_input.just_released(key_bindings.select_button) {
+        return;
+    }
+
+    // The select button also pans, so only a release close to the press counts as a click.
+    let (Some(press_position), Some(cursor)) = (tracker.press_position.take(), cursor) else {
+        return;
+    };
+    if press_position.distance(cursor) > selection_settings.click_tolerance {
+        return;
+    }
+
+    let (camera_transform, projection) = *camera;
+    let hit = pick_body(camera_transform, projection, window.size(), cursor, &bodies)
+        .and_then(|entity| bodies.get(entity).ok())
+        .map(|(entity, transform, _)| (entity, transform.translation()));
+
+    // A double-click is also a click, so it selects the body as well.
+    let extending = key_input.any_pressed(key_bindings.extend_selection.iter().copied());
+    if !extending {
+        for entity in &selected {
+            commands.entity(entity).remove::<Selected>();
+        }
+    }
+    if let Some((entity, _)) = hit {
+        if extending && selected.contains(entity) {
+            commands.entity(entity).remove::<Selected>();
+        } else {
+            commands.entity(entity).insert(Selected);
+        }
+    }
+    let Some((entity, position)) = hit else {
+        tracker.last_click = None;
+        camera_follow.0 = None;
+        return;
+    };
+    if key_input.any_pressed(key_bindings.follow_body.iter().copied()) {
+        camera_follow.0 = Some(entity);
+        // Following takes over the target, so a glide would only fight it.
+        camera_glide.0 = None;
+    }
+
+    // Real time, so the window is the same however fast the simulation is running.
+    let now = time.elapsed_secs_f64();
+    let double_clicked = tracker.last_click.is_some_and(|(last_entity, last_time)| {
+        last_entity == entity && now - last_time <= f64::from(selection_settings.double_click_window)
+    });
+    if double_clicked {
+        camera_settings.target = position;
+        // Recentering replaces any glide that would otherwise drag the target away again.
+        camera_glide.0 = None;
+        // A third click starts a new double-click rather than completing another.
+        tracker.last_click = None;
+    } else {
+        tracker.last_click = Some((entity, now));
+    }
+}
+
+/// A system to draw a ring around each selected body, turned to face the camera so it always
+/// reads as a circle. Gizmos only last a frame, so a body deselected is back to normal straight away.
+fn draw_selection_highlights(
+    mut gizmos: Gizmos,
+    selection_settings: Res<SelectionSettings>,
+    camera: Single<&GlobalTransform, With<Camera>>,
+    selected: Query<(&GlobalTransform, &Radius), With<Selected>>,
+) {
+    let camera_position = camera.translation();
+    for (transform, radius) in &selected {
+        let position = transform.translation();
+        // Circles are drawn in the XY plane, so Z is turned towards the camera.
+        let facing = Quat::from_rotation_arc(Vec3::Z, (camera_position - position).normalize_or(Vec3::Z));
+        gizmos.circle(
+            Isometry3d::new(position, facing),
+            radius.0 * selection_settings.highlight_scale,
+            selection_settings.highlight_color,
+        );
+    }
+}
+
+/// A system to link the two selected bodies with a spring, at rest at their current distance apart.
+#[allow(clippy::type_complexity)]
+fn link_selected_bodies(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    spring_settings: Res<SpringSettings>,
+    selected: Query<(Entity, &GlobalTransform), (With<Selected>, With<Body>)>,
+) {
+    if !key_input.just_pressed(key_bindings.link_spring) {
+        return;
+    }
+
+    let selected: Vec<_> = selected.iter().collect();
+    let &[(a, transform_a), (b, transform_b)] = selected.as_slice() else {
+        warn!("Select exactly two bodies to link them with a spring, {} are selected.", selected.len());
+        return;
+    };
+    commands.spawn(Spring {
+        a,
+        b,
+        rest_length: transform_a.translation().distance(transform_b.translation()),
+        stiffness: spring_settings.stiffness,
+        damping: spring_settings.damping,
+    });
+    info!("Linked bodies {a} and {b} with a spring.");
+}
+
+/// A system to hold the selected bodies in place, or let them go again.
+fn toggle_anchor(
+    mut commands: Commands,
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut selected: Query<(Entity, &Transform, &mut LastPos, Has<Anchored>), With<Selected>>,
+) {
+    if !key_input.just_pressed(key_bindings.toggle_anchor) {
+        return;
+    }
+
+    for (entity, transform, mut last_pos, anchored) in &mut selected {
+        if anchored {
+            commands.entity(entity).remove::<Anchored>();
+        } else {
+            // Bringing the body to rest too means it doesn't fly off at its old speed when let go.
+            last_pos.0 = transform.translation;
+            commands.entity(entity).insert(Anchored);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{HashSet, VecDeque};
+use bevy::{asset::RenderAssetUsages, prelude::*, render::mesh::PrimitiveTopology};
+use crate::{bindings::KeyBindings, bodies::{BaseColor, Body}};
+
+/// Settings for the ribbons trailing behind bodies along the paths they took.
+#[derive(Debug, Resource)]
+pub struct TrailSettings {
+    pub enabled: bool,
+    /// Most physics ticks a trail reaches back. Each one is two vertices of the ribbon.
+    pub max_points: usize,
+    /// Width of the ribbon at its newest end.
+    pub width: f32,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_points: 64,
+            width: 0.15,
+        }
+    }
+}
+
+/// A ribbon mesh following the body it holds, with the positions it was built from, oldest first.
+#[derive(Component)]
+struct Trail {
+    body: Entity,
+    points: VecDeque<Vec3>,
+}
+
+/// The vertex attributes of a ribbon mesh.
+#[derive(Debug, Default)]
+struct RibbonVertices {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+}
+
+pub struct TrailsPlugin;
+
+impl Plugin for TrailsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrailSettings>()
+            .add_systems(Update, toggle_trails)
+            // Bodies only move on physics ticks, so there is nothing new to add in between.
+            .add_systems(FixedPostUpdate, update_trails);
+    }
+}
+
+/// A system to show or hide the trails.
+fn toggle_trails(
+    key_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut trail_settings: ResMut<TrailSettings>,
+) {
+    if key_input.just_pressed(key_bindings.toggle_trails) {
+        trail_settings.enabled = !trail_settings.enabled;
+        info!("Trails: {}", trail_settings.enabled);
+    }
+}
+
+/// A system to extend each body's trail to where it is now and rebuild its ribbon, and to give
+/// new bodies a trail of their own. Trails are removed once their body despawns or trails are
+/// turned off, and each one keeps the same mesh for as long as it lasts.
+fn update_trails(
+    mut commands: Commands,
+    trail_settings: Res<TrailSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    camera: Option<Single<&GlobalTransform, With<Camera>>>,
+    bodies: Query<(Entity, &GlobalTransform, &BaseColor), With<Body>>,
+    mut trails: Query<(Entity, &mut Trail, &Mesh3d)>,
+) {
+    let camera_position = camera.map_or(Vec3::ZERO, |camera| camera.translation());
+
+    let mut trailed = HashSet::new();
+    for (trail_entity, mut trail, mesh) in &mut trails {
+        let body = bodies.get(trail.body).ok().filter(|_| trail_settings.enabled);
+        let Some((_, transform, _)) = body else {
+            commands.entity(trail_entity).despawn();
+            continue;
+        };
+        trailed.insert(trail.body);
+
+        // Physics ticks keep coming while paused, and a body that hasn't moved shouldn't eat its own trail.
+        if trail.points.back() != Some(&transform.translation()) {
+            trail.points.push_back(transform.translation());
+        }
+        while trail.points.len() > trail_settings.max_points.max(2) {
+            trail.points.pop_front();
+        }
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            build_ribbon(mesh, &trail.points, camera_position, trail_settings.width);
+        }
+    }
+
+    if !trail_settings.enabled {
+        return;
+    }
+    for (entity, transform, base_color) in &bodies {
+        if trailed.contains(&entity) {
+            continue;
+        }
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleStrip, RenderAssetUsages::default());
+        let points = VecDeque::from([transform.translation()]);
+        build_ribbon(&mut mesh, &points, camera_position, trail_settings.width);
+        commands.spawn((
+            Trail { body: entity, points },
+            Mesh3d(meshes.add(mesh)),
+            // Vertex colors carry the fade, and the base color tints the whole ribbon.
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: base_color.0,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                cull_mode: None,
+                ..default()
+            })),
+            Transform::IDENTITY,
+        ));
+    }
+}
+
+/// A function to rebuild a ribbon mesh along the points, turned to face the camera and fading
+/// out towards the oldest point.
+fn build_ribbon(mesh: &mut Mesh, points: &VecDeque<Vec3>, camera_position: Vec3, width: f32) {
+    let vertices = ribbon_vertices(points, camera_position, width);
+    // Replacing the attributes keeps the same mesh asset, rather than adding a new one each tick.
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices.positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vertices.normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vertices.colors);
+}
+
+/// A function to lay out a triangle strip along the points, two vertices either side of each.
+/// The ribbon narrows and fades from the newest point back to nothing at the oldest.
+fn ribbon_vertices(points: &VecDeque<Vec3>, camera_position: Vec3, width: f32) -> RibbonVertices {
+    let mut vertices = RibbonVertices {
+        positions: Vec::with_capacity(points.len() * 2),
+        normals: Vec::with_capacity(points.len() * 2),
+        colors: Vec::with_capacity(points.len() * 2),
+    };
+    // A single point has no direction to lay a ribbon along, so it is left as an invisible sliver
+    // rather than a mesh with no vertices at all.
+    if points.len() < 2 {
+        for &point in points {
+            vertices.positions.extend([point.to_array(); 2]);
+            vertices.normals.extend([Vec3::Z.to_array(); 2]);
+            vertices.colors.extend([[1.0, 1.0, 1.0, 0.0]; 2]);
+        }
+        return vertices;
+    }
+
+    let last = points.len() - 1;
+    for (index, &point) in points.iter().enumerate() {
+        // Each point is laid across the direction of travel through it.
+        let direction = points[(index + 1).min(last)] - points[index.saturating_sub(1)];
+        let to_camera = (camera_position - point).normalize_or(Vec3::Z);
+        let side = direction.cross(to_camera).normalize_or(to_camera.any_orthonormal_vector());
+
+        let age = index as f32 / last as f32;
+        let half_width = side * width * age / 2.0;
+        for offset in [half_width, -half_width] {
+            vertices.positions.push((point + offset).to_array());
+            vertices.normals.push(to_camera.to_array());
+            vertices.colors.push([1.0, 1.0, 1.0, age]);
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ribbons_fade_towards_the_oldest_point() {
+        let points = VecDeque::from([Vec3::ZERO, Vec3::X, Vec3::X * 2.0]);
+        let RibbonVertices { positions, normals, colors } = ribbon_vertices(&points, Vec3::new(1.0, 0.0, 10.0), 0.2);
+        assert_eq!(positions.len(), 6);
+        assert_eq!(normals.len(), 6);
+
+        // The oldest end is invisible and pinched to a point, and the newest is opaque and full width.
+        assert_eq!(colors[0][3], 0.0);
+        assert_eq!(positions[0], positions[1]);
+        assert_eq!(colors[5][3], 1.0);
+        let newest_width = Vec3::from(positions[4]).distance(Vec3::from(positions[5]));
+        assert!((newest_width - 0.2).abs() < 1e-5);
+        // Travelling along X and seen from along Z, the ribbon spreads along Y.
+        assert!((positions[4][1].abs() - 0.1).abs() < 1e-2);
+
+        let RibbonVertices { positions, colors, .. } = ribbon_vertices(&VecDeque::from([Vec3::ZERO]), Vec3::Z, 0.2);
+        assert_eq!(positions, [[0.0; 3]; 2]);
+        assert!(colors.iter().all(|color| color[3] == 0.0));
+    }
+}